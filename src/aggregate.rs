@@ -0,0 +1,366 @@
+use std::collections::HashMap;
+
+use crate::{
+    albastream::{Error, ErrorKind},
+    db_response::{DBResponse, Row},
+    handler::SearchBuilder,
+    types::{AlbaTypes, Numeric},
+};
+
+/// A number a column can be summed into.
+///
+/// Every integer variant of `AlbaTypes` widens into the integer implementations as long as the values fit,
+/// `f64` accepts every numeric variant.
+pub trait Summable: Sized + Copy {
+    fn zero() -> Self;
+    fn add_value(self, value: &AlbaTypes) -> Result<Self, Error>;
+}
+
+macro_rules! summable_integer {
+    ($($t:ty),*) => {
+        $(
+            impl Summable for $t {
+                fn zero() -> Self {
+                    0
+                }
+                fn add_value(self, value: &AlbaTypes) -> Result<Self, Error> {
+                    let v = match value.numeric() {
+                        Some(Numeric::Signed(v)) => <$t>::try_from(v).ok(),
+                        Some(Numeric::Unsigned(v)) => <$t>::try_from(v).ok(),
+                        Some(Numeric::Float(_)) => return Err(Error::new(ErrorKind::InvalidInput, "Cannot sum a float value into an integer")),
+                        None => return Err(Error::new(ErrorKind::InvalidInput, "Cannot sum a non numeric value")),
+                    };
+                    match v.and_then(|v| self.checked_add(v)) {
+                        Some(v) => Ok(v),
+                        None => Err(Error::new(ErrorKind::InvalidInput, concat!("The sum overflows ", stringify!($t)))),
+                    }
+                }
+            }
+        )*
+    };
+}
+summable_integer!(i32, i64, i128, u32, u64, u128);
+
+impl Summable for f64 {
+    fn zero() -> Self {
+        0.0
+    }
+    fn add_value(self, value: &AlbaTypes) -> Result<Self, Error> {
+        match value.numeric() {
+            Some(Numeric::Signed(v)) => Ok(self + v as f64),
+            Some(Numeric::Unsigned(v)) => Ok(self + v as f64),
+            Some(Numeric::Float(v)) => Ok(self + v),
+            None => Err(Error::new(ErrorKind::InvalidInput, "Cannot sum a non numeric value")),
+        }
+    }
+}
+
+/// An aggregation computed for every group of a `group_by`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Agg {
+    /// Row count, as `AlbaTypes::U64`.
+    Count,
+    /// Sum of a numeric column, as `AlbaTypes::I128` for integer columns and `AlbaTypes::F64` once a float is met.
+    Sum(String),
+    /// Smallest value of a column, following `AlbaTypes::total_cmp`.
+    Min(String),
+    /// Biggest value of a column, following `AlbaTypes::total_cmp`.
+    Max(String),
+    /// Mean of a numeric column, as `AlbaTypes::F64`.
+    Avg(String),
+}
+
+/// Aggregations over the payload of a `Search` response.
+///
+/// The rows are decoded one by one from the payload for every aggregation, so only the encoded bytes are kept
+/// in memory. Get the payload with the `execute_raw` method of the clients.
+pub struct Aggregate {
+    columns: Vec<String>,
+    payload: Vec<u8>,
+}
+
+impl Aggregate {
+    /// `columns` is the projection of the search that produced `payload`, in order.
+    pub fn new(columns: Vec<String>, payload: Vec<u8>) -> Self {
+        Aggregate { columns, payload }
+    }
+    /// Aggregate over an already decoded response.
+    pub fn from_response(columns: Vec<String>, response: &DBResponse) -> Self {
+        Aggregate { columns, payload: response.encode() }
+    }
+
+    fn index(&self, column: &str) -> Result<usize, Error> {
+        match self.columns.iter().position(|c| c == column) {
+            Some(i) => Ok(i),
+            None => Err(Error::new(ErrorKind::InvalidInput, &format!("The column {} is not part of the search projection", column))),
+        }
+    }
+
+    fn fold<T, F: FnMut(T, &Row) -> Result<T, Error>>(&self, init: T, mut f: F) -> Result<T, Error> {
+        let mut acc = init;
+        for row in DBResponse::iter_rows(&self.payload) {
+            acc = f(acc, &row?)?;
+        }
+        Ok(acc)
+    }
+
+    pub fn count(&self) -> Result<usize, Error> {
+        self.fold(0usize, |c, _| Ok(c + 1))
+    }
+
+    /// Sum of `column`, e.g. `sum::<i64>("price")`.
+    pub fn sum<T: Summable>(&self, column: &str) -> Result<T, Error> {
+        let index = self.index(column)?;
+        self.fold(T::zero(), |acc, row| acc.add_value(value(row, index)?))
+    }
+
+    /// Smallest value of `column`, `None` when there is no row.
+    pub fn min(&self, column: &str) -> Result<Option<AlbaTypes>, Error> {
+        let index = self.index(column)?;
+        self.fold(None, |acc: Option<AlbaTypes>, row| {
+            let v = value(row, index)?;
+            Ok(match acc {
+                Some(a) if a.total_cmp(v).is_le() => Some(a),
+                _ => Some(v.clone()),
+            })
+        })
+    }
+
+    /// Biggest value of `column`, `None` when there is no row.
+    pub fn max(&self, column: &str) -> Result<Option<AlbaTypes>, Error> {
+        let index = self.index(column)?;
+        self.fold(None, |acc: Option<AlbaTypes>, row| {
+            let v = value(row, index)?;
+            Ok(match acc {
+                Some(a) if a.total_cmp(v).is_ge() => Some(a),
+                _ => Some(v.clone()),
+            })
+        })
+    }
+
+    /// Mean of `column`, `None` when there is no row.
+    pub fn avg(&self, column: &str) -> Result<Option<f64>, Error> {
+        let index = self.index(column)?;
+        let (sum, count) = self.fold((0f64, 0usize), |(s, c), row| Ok((s.add_value(value(row, index)?)?, c + 1)))?;
+        Ok(if count == 0 { None } else { Some(sum / count as f64) })
+    }
+
+    /// Group the rows by the values of `column`.
+    pub fn group_by(&self, column: &str) -> GroupBy<'_> {
+        GroupBy { aggregate: self, column: column.to_string() }
+    }
+}
+
+impl SearchBuilder {
+    /// Aggregations over the raw response payload of this search, see `request`.
+    ///
+    /// The `filters`, `distinct`, `order_by`, `offset` and `limit` of the search are applied to the rows first, as
+    /// `post_process` does, which decodes the whole payload; without them the rows stay encoded.
+    pub fn aggregate(&self, payload: Vec<u8>) -> Result<Aggregate, Error> {
+        let projection = self.projection()?;
+        if self.filters.is_empty() && !self.distinct && self.order_by.is_empty() && self.offset == 0 && self.limit.is_none() {
+            return Ok(Aggregate::new(projection, payload));
+        }
+        let response = self.post_process(DBResponse::from_bytes(&payload)?)?;
+        // `post_process` drops the columns only added to the projection for the filters and the ordering
+        let columns = if self.column_names.is_empty() { projection } else { self.column_names.clone() };
+        Ok(Aggregate::from_response(columns, &response))
+    }
+}
+
+fn value(row: &Row, index: usize) -> Result<&AlbaTypes, Error> {
+    match row.0.get(index) {
+        Some(v) => Ok(v),
+        None => Err(Error::new(ErrorKind::UnexpectedEof, "The row is shorter than the search projection")),
+    }
+}
+
+enum Accumulator {
+    Count(u64),
+    Sum(Option<i128>, f64, bool),
+    Min(Option<AlbaTypes>),
+    Max(Option<AlbaTypes>),
+    Avg(f64, u64),
+}
+
+impl Accumulator {
+    fn new(agg: &Agg) -> Self {
+        match agg {
+            Agg::Count => Accumulator::Count(0),
+            Agg::Sum(_) => Accumulator::Sum(Some(0), 0.0, false),
+            Agg::Min(_) => Accumulator::Min(None),
+            Agg::Max(_) => Accumulator::Max(None),
+            Agg::Avg(_) => Accumulator::Avg(0.0, 0),
+        }
+    }
+    fn push(&mut self, v: Option<&AlbaTypes>) -> Result<(), Error> {
+        match (self, v) {
+            (Accumulator::Count(c), _) => *c += 1,
+            (Accumulator::Sum(int, float, is_float), Some(v)) => {
+                *float = float.add_value(v)?;
+                match v.numeric() {
+                    Some(Numeric::Float(_)) => *is_float = true,
+                    _ => *int = int.and_then(|i| i.add_value(v).ok()),
+                }
+            }
+            (Accumulator::Min(m), Some(v)) => {
+                if m.as_ref().is_none_or(|m| v.total_cmp(m).is_lt()) {
+                    *m = Some(v.clone())
+                }
+            }
+            (Accumulator::Max(m), Some(v)) => {
+                if m.as_ref().is_none_or(|m| v.total_cmp(m).is_gt()) {
+                    *m = Some(v.clone())
+                }
+            }
+            (Accumulator::Avg(s, c), Some(v)) => {
+                *s = s.add_value(v)?;
+                *c += 1;
+            }
+            (_, None) => return Err(Error::new(ErrorKind::UnexpectedEof, "The row is shorter than the search projection")),
+        }
+        Ok(())
+    }
+    fn finish(self) -> AlbaTypes {
+        match self {
+            Accumulator::Count(c) => AlbaTypes::U64(c),
+            // the integer sum falls back to the float one when a float is met or it overflows i128
+            Accumulator::Sum(Some(i), _, false) => AlbaTypes::I128(i),
+            Accumulator::Sum(_, f, _) => AlbaTypes::F64(f),
+            // a group always has at least one row
+            Accumulator::Min(m) | Accumulator::Max(m) => m.unwrap_or(AlbaTypes::Bool(false)),
+            Accumulator::Avg(s, c) => AlbaTypes::F64(s / c as f64),
+        }
+    }
+}
+
+/// Rows grouped by the values of a column, see `Aggregate::group_by`.
+pub struct GroupBy<'a> {
+    aggregate: &'a Aggregate,
+    column: String,
+}
+
+impl GroupBy<'_> {
+    /// Compute `aggs` for every group.
+    ///
+    /// Each row of the response is `[group value, agg0, agg1, ...]`, the groups keep the order in which their
+    /// first row was met.
+    pub fn agg(&self, aggs: &[Agg]) -> Result<DBResponse, Error> {
+        let key_index = self.aggregate.index(&self.column)?;
+        let mut indexes = Vec::with_capacity(aggs.len());
+        for agg in aggs {
+            indexes.push(match agg {
+                Agg::Count => None,
+                Agg::Sum(c) | Agg::Min(c) | Agg::Max(c) | Agg::Avg(c) => Some(self.aggregate.index(c)?),
+            });
+        }
+        let mut positions: HashMap<Vec<u8>, usize> = HashMap::new();
+        let mut groups: Vec<(AlbaTypes, Vec<Accumulator>)> = Vec::new();
+        self.aggregate.fold((), |_, row| {
+            let key = value(row, key_index)?;
+            let position = *positions.entry(key.as_bytes()).or_insert_with(|| {
+                groups.push((key.clone(), aggs.iter().map(Accumulator::new).collect()));
+                groups.len() - 1
+            });
+            for (acc, index) in groups[position].1.iter_mut().zip(indexes.iter()) {
+                acc.push(match index {
+                    Some(i) => Some(value(row, *i)?),
+                    None => None,
+                })?;
+            }
+            Ok(())
+        })?;
+        Ok(DBResponse::new(
+            groups
+                .into_iter()
+                .map(|(key, accs)| {
+                    let mut row = vec![key];
+                    row.extend(accs.into_iter().map(Accumulator::finish));
+                    Row::new(row)
+                })
+                .collect(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logical_operators::LogicalOperator;
+
+    fn columns() -> Vec<String> {
+        vec!["kind".to_string(), "n".to_string()]
+    }
+    fn payload(rows: Vec<(&str, AlbaTypes)>) -> Vec<u8> {
+        DBResponse::new(rows.into_iter().map(|(k, n)| Row::new(vec![AlbaTypes::String(k.to_string()), n])).collect()).encode()
+    }
+
+    #[test]
+    fn integers_widen_into_the_sum() {
+        let aggregate = Aggregate::new(columns(), payload(vec![("a", AlbaTypes::U8(200)), ("b", AlbaTypes::I32(-50)), ("a", AlbaTypes::U64(1 << 40))]));
+        assert_eq!(aggregate.sum::<i64>("n").unwrap(), (1i64 << 40) + 150);
+        assert_eq!(aggregate.sum::<f64>("n").unwrap(), (1u64 << 40) as f64 + 150.0);
+        // -50 doesn't fit an unsigned sum and 2^40 doesn't fit an i32 one
+        assert_eq!(aggregate.sum::<u64>("n").unwrap_err().kind(), ErrorKind::InvalidInput);
+        assert_eq!(aggregate.sum::<i32>("n").unwrap_err().kind(), ErrorKind::InvalidInput);
+        assert_eq!(aggregate.count().unwrap(), 3);
+        assert_eq!(aggregate.min("n").unwrap(), Some(AlbaTypes::I32(-50)));
+        assert_eq!(aggregate.max("n").unwrap(), Some(AlbaTypes::U64(1 << 40)));
+    }
+
+    #[test]
+    fn floats_are_only_summed_as_floats() {
+        let aggregate = Aggregate::new(columns(), payload(vec![("a", AlbaTypes::U8(1)), ("a", AlbaTypes::F64(0.5))]));
+        assert_eq!(aggregate.sum::<i128>("n").unwrap_err().kind(), ErrorKind::InvalidInput);
+        assert_eq!(aggregate.sum::<f64>("n").unwrap(), 1.5);
+        assert_eq!(aggregate.avg("n").unwrap(), Some(0.75));
+        assert_eq!(aggregate.sum::<f64>("missing").unwrap_err().kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn groups_sum_as_integers_until_a_float_is_met() {
+        let aggregate = Aggregate::new(
+            columns(),
+            payload(vec![
+                ("a", AlbaTypes::U8(2)),
+                ("b", AlbaTypes::I64(-1)),
+                ("a", AlbaTypes::U16(3)),
+                ("b", AlbaTypes::F32(0.5)),
+                ("c", AlbaTypes::U128(u128::MAX)),
+                ("c", AlbaTypes::U128(u128::MAX)),
+            ]),
+        );
+        let groups = aggregate.group_by("kind").agg(&[Agg::Count, Agg::Sum("n".to_string()), Agg::Min("n".to_string())]).unwrap();
+        let rows: Vec<Vec<AlbaTypes>> = groups.row_list.into_iter().map(|r| r.0).collect();
+        assert_eq!(rows[0], vec![AlbaTypes::String("a".to_string()), AlbaTypes::U64(2), AlbaTypes::I128(5), AlbaTypes::U8(2)]);
+        assert_eq!(rows[1], vec![AlbaTypes::String("b".to_string()), AlbaTypes::U64(2), AlbaTypes::F64(-0.5), AlbaTypes::I64(-1)]);
+        // the integer sum overflows i128 and falls back to the float one
+        assert_eq!(rows[2][2], AlbaTypes::F64(u128::MAX as f64 * 2.0));
+        assert_eq!(rows.len(), 3);
+    }
+
+    #[test]
+    fn searches_aggregate_their_processed_rows() {
+        // the server answers with the listed columns first, then the ones the filters added
+        let rows = DBResponse::new(
+            [("a", 1), ("b", 2), ("a", 3), ("a", 4)]
+                .into_iter()
+                .map(|(k, n)| Row::new(vec![AlbaTypes::U8(n), AlbaTypes::String(k.to_string())]))
+                .collect(),
+        )
+        .encode();
+        let search = SearchBuilder::new()
+            .add_container("c".to_string())
+            .add_column_name("n".to_string())
+            .add_condition_values("kind".to_string(), LogicalOperator::NotStringContains, vec![AlbaTypes::String("b".to_string())], true)
+            .limit(2);
+        // the filter column is part of the payload but not of the aggregated columns
+        let aggregate = search.aggregate(rows.clone()).unwrap();
+        assert_eq!(aggregate.count().unwrap(), 2);
+        assert_eq!(aggregate.sum::<u64>("n").unwrap(), 4);
+        assert_eq!(aggregate.sum::<u64>("kind").unwrap_err().kind(), ErrorKind::InvalidInput);
+
+        let plain = SearchBuilder::new().add_container("c".to_string()).add_column_name("n".to_string()).add_column_name("kind".to_string());
+        assert_eq!(plain.aggregate(rows).unwrap().sum::<u64>("n").unwrap(), 10);
+    }
+}
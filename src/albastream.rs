@@ -1,15 +1,19 @@
 use std::fmt;
-use std::string::ToString;
 
 pub type CompiledAlba = Vec<u8>;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ErrorKind{
     Other,
     InvalidInput,
-    UnexpectedEof
+    UnexpectedEof,
+    /// The request could not be sent or its reply could not be read.
+    Connection,
+    /// The request was not sent since the circuit breaker of the client is open, see `breaker::CircuitBreaker`.
+    CircuitOpen
 }
 
+#[derive(Clone)]
 pub struct Error{
     kind : ErrorKind,
     message : String
@@ -18,16 +22,24 @@ impl Error{
     pub fn new(kind : ErrorKind, message : &str) -> Error{
         Error{kind,message:message.to_string()}
     }
+    pub fn kind(&self) -> ErrorKind{
+        self.kind
+    }
+    pub fn message(&self) -> &str{
+        &self.message
+    }
 }
-impl ToString for Error {
-    fn to_string(&self) -> String {
-        return format!("Error<Kind:{}>: {}",
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Error<Kind:{}>: {}",
         match self.kind{
             ErrorKind::Other => "Other",
             ErrorKind::InvalidInput => "InvalidInput",
-            ErrorKind::UnexpectedEof => "UnexpectedEof"
+            ErrorKind::UnexpectedEof => "UnexpectedEof",
+            ErrorKind::Connection => "Connection",
+            ErrorKind::CircuitOpen => "CircuitOpen"
         },
-        self.message.to_string()
+        self.message
     )
     }
 }
@@ -35,6 +47,7 @@ impl ToString for Error {
 
 impl fmt::Debug for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.to_string())
+        write!(f, "{}", self)
     }
-}
\ No newline at end of file
+}
+impl std::error::Error for Error {}
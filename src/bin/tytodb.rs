@@ -0,0 +1,297 @@
+use std::{fs, io::IsTerminal, path::PathBuf, process::ExitCode};
+
+use clap::{Parser, Subcommand, ValueEnum};
+use rustyline::{DefaultEditor, error::ReadlineError};
+use tytodb_client::{
+    client_thread::Client,
+    commands::Commands,
+    db_response::DBResponse,
+    inspect::{inspect_command, inspect_reply, inspect_response, parse_dump},
+    parser::{ParseError, parse_script},
+    secret::Secret,
+};
+
+/// Command line client for TytoDB: runs the text query language against a server.
+#[derive(Parser)]
+#[command(name = "tytodb", version)]
+struct Cli {
+    /// Address of the TytoDB server.
+    #[arg(long, default_value = "127.0.0.1:4287")]
+    host: String,
+    /// File holding the secret key of the server: its 32 bytes, or the key in hex or base64.
+    #[arg(long)]
+    secret_file: Option<PathBuf>,
+    /// How the rows are printed.
+    #[arg(long, value_enum, default_value_t = Format::Table)]
+    format: Format,
+    /// Run these `;` separated statements and exit instead of starting the REPL.
+    #[arg(short, long)]
+    execute: Option<String>,
+    /// Run the statements of a script file and exit instead of starting the REPL.
+    #[arg(short, long, conflicts_with = "execute")]
+    file: Option<PathBuf>,
+    /// History file of the REPL, defaults to `~/.tytodb_history`.
+    #[arg(long)]
+    history: Option<PathBuf>,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Decode a dump of a compiled command or of a response, printing every field with its byte offset.
+    Inspect {
+        /// The dump: hex (`05 02 00`, `0x05, 0x02`) or a Rust decimal list (`[5, 2, 0]`).
+        /// Read from the standard input when neither it nor `--file` is given.
+        dump: Option<String>,
+        /// Read the dump from a file, either a text dump or the raw bytes.
+        #[arg(short, long, conflicts_with = "dump")]
+        file: Option<PathBuf>,
+        /// What the bytes are.
+        #[arg(long, value_enum, default_value_t = Payload::Command)]
+        kind: Payload,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Payload {
+    /// A compiled command (`CompiledAlba`).
+    Command,
+    /// The rows of a response, as returned by `execute_raw`.
+    Response,
+    /// A raw server reply, starting with its status byte.
+    Reply,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Table,
+    Json,
+    Csv,
+}
+
+const HELP: &str = "Statements end with `;` and may span several lines:
+  SELECT id, content FROM c WHERE id >= 5 AND content &> 'legal';
+  INSERT INTO c (id, content) VALUES (1, 'a'), (2, 'b');
+  UPDATE c SET content = 'b' WHERE id = 1;
+  DELETE FROM c WHERE id in (1, 2);
+  CREATE CONTAINER c (id BIGINT, content MEDIUM_STRING);
+  DROP CONTAINER c;
+  COMMIT [c]; ROLLBACK [c];
+  BEGIN [TRANSACTION]; ...; END;
+Meta commands:
+  \\format table|json|csv   change the output format
+  \\help                    show this help
+  \\quit                    leave (Ctrl-D works too)";
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    if let Some(Command::Inspect { dump, file, kind }) = &cli.command {
+        return inspect(dump.as_deref(), file.as_ref(), *kind);
+    }
+    let secret_file = match &cli.secret_file {
+        Some(f) => f,
+        None => {
+            eprintln!("error: --secret-file is required to connect");
+            return ExitCode::FAILURE;
+        }
+    };
+    let secret = match Secret::from_file(secret_file) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("error: {}", e.message());
+            return ExitCode::FAILURE;
+        }
+    };
+    let client = match Client::connect(&cli.host, secret) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("error: could not connect to {}: {}", cli.host, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let script = match (&cli.execute, &cli.file) {
+        (Some(s), _) => Some(s.clone()),
+        (None, Some(path)) => match fs::read_to_string(path) {
+            Ok(s) => Some(s),
+            Err(e) => {
+                eprintln!("error: could not read {}: {}", path.display(), e);
+                return ExitCode::FAILURE;
+            }
+        },
+        (None, None) if !std::io::stdin().is_terminal() => match std::io::read_to_string(std::io::stdin()) {
+            Ok(s) => Some(s),
+            Err(e) => {
+                eprintln!("error: could not read the standard input: {}", e);
+                return ExitCode::FAILURE;
+            }
+        },
+        (None, None) => None,
+    };
+    match script {
+        Some(script) => {
+            if run(&client, &script, cli.format) { ExitCode::SUCCESS } else { ExitCode::FAILURE }
+        }
+        None => repl(&client, cli.format, cli.history),
+    }
+}
+
+fn inspect(dump: Option<&str>, file: Option<&PathBuf>, kind: Payload) -> ExitCode {
+    let bytes = match (dump, file) {
+        (Some(d), _) => parse_dump(d).map_err(|e| e.to_string()),
+        (None, Some(path)) => match fs::read(path) {
+            // a file is a text dump when it reads as one, the raw bytes otherwise
+            Ok(raw) => Ok(std::str::from_utf8(&raw).ok().and_then(|t| parse_dump(t).ok()).unwrap_or(raw)),
+            Err(e) => Err(format!("could not read {}: {}", path.display(), e)),
+        },
+        (None, None) => std::io::read_to_string(std::io::stdin()).map_err(|e| e.to_string()).and_then(|t| parse_dump(&t).map_err(|e| e.to_string())),
+    };
+    let bytes = match bytes {
+        Ok(b) => b,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let inspection = match kind {
+        Payload::Command => inspect_command(&bytes),
+        Payload::Response => inspect_response(&bytes),
+        Payload::Reply => inspect_reply(&bytes),
+    };
+    println!("{}", inspection);
+    if inspection.failure.is_some() { ExitCode::FAILURE } else { ExitCode::SUCCESS }
+}
+
+fn repl(client: &Client, format: Format, history: Option<PathBuf>) -> ExitCode {
+    let mut format = format;
+    let mut editor = match DefaultEditor::new() {
+        Ok(e) => e,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let history = history.or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".tytodb_history")));
+    if let Some(h) = &history {
+        let _ = editor.load_history(h);
+    }
+    println!("Connected. Type \\help for help, \\quit to leave.");
+    let mut buffer = String::new();
+    loop {
+        let prompt = if buffer.is_empty() { "tytodb> " } else { "     -> " };
+        let line = match editor.readline(prompt) {
+            Ok(l) => l,
+            Err(ReadlineError::Interrupted) => {
+                buffer.clear();
+                continue;
+            }
+            Err(ReadlineError::Eof) => break,
+            Err(e) => {
+                eprintln!("error: {}", e);
+                break;
+            }
+        };
+        if buffer.is_empty() {
+            let meta = line.trim();
+            if meta.is_empty() {
+                continue;
+            }
+            if meta.starts_with('\\') {
+                let _ = editor.add_history_entry(meta);
+                match meta.split_whitespace().collect::<Vec<&str>>().as_slice() {
+                    ["\\q"] | ["\\quit"] => break,
+                    ["\\h"] | ["\\help"] => println!("{}", HELP),
+                    ["\\format", f] => match Format::from_str(f, true) {
+                        Ok(f) => format = f,
+                        Err(_) => eprintln!("error: unknown format {}, expected table, json or csv", f),
+                    },
+                    _ => eprintln!("error: unknown meta command, see \\help"),
+                }
+                continue;
+            }
+        }
+        buffer.push_str(&line);
+        buffer.push('\n');
+        if !complete(&buffer) {
+            continue;
+        }
+        let _ = editor.add_history_entry(buffer.trim_end());
+        run(client, &buffer, format);
+        buffer.clear();
+    }
+    if let Some(h) = &history {
+        let _ = editor.save_history(h);
+    }
+    ExitCode::SUCCESS
+}
+
+/// Whether the buffer holds whole statements: it ends with `;` and doesn't stop in the middle of a `BEGIN` batch.
+fn complete(buffer: &str) -> bool {
+    if !buffer.trim_end().ends_with(';') {
+        return false;
+    }
+    match parse_script(buffer) {
+        Err(e) => !e.message.ends_with("found the end of the input"),
+        Ok(_) => true,
+    }
+}
+
+/// Runs every statement of `script`, stopping at the first failure. Returns whether all of them succeeded.
+fn run(client: &Client, script: &str, format: Format) -> bool {
+    let commands = match parse_script(script) {
+        Ok(c) => c,
+        Err(e) => {
+            report(script, &e);
+            return false;
+        }
+    };
+    for command in commands {
+        let compiled = match command.compile() {
+            Ok(c) => c,
+            Err(e) => {
+                eprintln!("error: {}", e);
+                return false;
+            }
+        };
+        match client.execute(compiled) {
+            Ok(response) => print(&command, &response, format),
+            Err(e) => {
+                eprintln!("error: {}\n  in: {}", e, command);
+                return false;
+            }
+        }
+    }
+    true
+}
+
+/// Prints a parse error under the offending line, with a caret at its column.
+fn report(script: &str, e: &ParseError) {
+    eprintln!("error: {}", e);
+    if let Some(line) = script.lines().nth(e.line - 1) {
+        eprintln!("  {}\n  {}^", line, " ".repeat(e.column - 1));
+    }
+}
+
+fn print(command: &Commands, response: &DBResponse, format: Format) {
+    let columns = match command {
+        Commands::Search(s) => s.col_nam.clone(),
+        _ => Vec::new(),
+    };
+    if response.row_list.is_empty() && !matches!(command, Commands::Search(_)) {
+        if format == Format::Table {
+            println!("OK");
+        }
+        return;
+    }
+    let written = match format {
+        Format::Table => {
+            println!("{}", response.to_table(&columns));
+            return;
+        }
+        Format::Json => response.write_jsonl(std::io::stdout().lock(), &columns),
+        Format::Csv => response.write_csv(std::io::stdout().lock(), &columns),
+    };
+    if let Err(e) = written {
+        eprintln!("error: {}", e.message());
+    }
+}
@@ -0,0 +1,219 @@
+use std::{io::Error as IoError, path::Path};
+use tokio::runtime::Runtime;
+
+use crate::{albastream::{CompiledAlba, Error}, breaker::CircuitBreaker, bulk::{BulkInsert, BulkReport}, cache::QueryCache, client_tokio::{Client as AsyncClient, Transaction as AsyncTransaction}, db_response::DBResponse, dump::{Progress, Restore}, endpoints::Endpoints, intercept::Interceptor, metrics::Metrics, migrate::{MigrationReport, Migrations, StatusReport}, retry::{Retry, RetryPolicy}, secret::Secret, types::AlbaTypes, handler::{CommitBuilder, CreateContainerBuilder, CreateRowBuilder, DeleteContainerBuilder, DeleteRowBuilder, EditRowBuilder, RollbackBuilder, SearchBuilder, BatchCreateRowsBuilder, BatchBuilder}};
+#[cfg(feature="import")]
+use crate::import::{ImportReport, Importer};
+
+/// A blocking client with the API of `client_thread::Client`, running a `client_tokio::Client` on its own runtime.
+///
+/// The runtime has one worker thread, which runs the heartbeat and the connections of `bulk_insert` between the
+/// calls. The methods block the calling thread, so they must not be called from within an async runtime, and the
+/// client must not be dropped there either.
+pub struct Client{
+    client : AsyncClient,
+    runtime : Runtime,
+}
+
+impl Client {
+    pub fn connect<S : Into<Secret>>(host : &str, password : S) -> Result<Client, IoError>{
+        Client::connect_endpoints(&Endpoints::new(host), password)
+    }
+    /// Connect to a primary with standbys and replicas, see `Endpoints`. Fails when none of the primary and the
+    /// standbys, or none of the replicas, can be reached.
+    pub fn connect_endpoints<S : Into<Secret>>(endpoints : &Endpoints, password : S) -> Result<Client, IoError>{
+        let runtime = tokio::runtime::Builder::new_multi_thread().worker_threads(1).thread_name("tytodb-blocking").enable_all().build()?;
+        let client = runtime.block_on(AsyncClient::connect_endpoints(endpoints, password))?;
+        Ok(Client{client,runtime})
+    }
+    /// Report the requests, reconnections, heartbeats and connections of the client to `metrics`.
+    pub fn with_metrics(self, metrics : Metrics) -> Client{
+        Client{client:self.client.with_metrics(metrics),..self}
+    }
+    /// Register an interceptor, running around every request after the ones registered before, see `Interceptor`.
+    pub fn with_interceptor<I : Interceptor + 'static>(self, interceptor : I) -> Client{
+        Client{client:self.client.with_interceptor(interceptor),..self}
+    }
+    /// Serve repeated searches from `cache`, see `QueryCache`.
+    pub fn with_cache(self, cache : QueryCache) -> Client{
+        Client{client:self.client.with_cache(cache),..self}
+    }
+    pub fn cache(&self) -> Option<&QueryCache>{
+        self.client.cache()
+    }
+    /// Fail fast with `ErrorKind::CircuitOpen` while the server can't be reached, see `CircuitBreaker`.
+    pub fn with_circuit_breaker(self, breaker : CircuitBreaker) -> Client{
+        Client{client:self.client.with_circuit_breaker(breaker),..self}
+    }
+    pub fn circuit_breaker(&self) -> Option<&CircuitBreaker>{
+        self.client.circuit_breaker()
+    }
+    /// Record the values of the commands in the `tytodb.execute` spans, they are left out by default since they
+    /// may hold personal data or secrets.
+    #[cfg(feature="tracing")]
+    pub fn with_traced_values(self, traced : bool) -> Client{
+        Client{client:self.client.with_traced_values(traced),..self}
+    }
+    /// Replace the retry policy, `RetryPolicy::default()` unless set.
+    pub fn with_retry_policy(self, policy : RetryPolicy) -> Client{
+        Client{client:self.client.with_retry_policy(policy),..self}
+    }
+    pub fn retry_policy(&self) -> &RetryPolicy{
+        self.client.retry_policy()
+    }
+    pub fn execute(&self,compiled_command : CompiledAlba) -> Result<DBResponse,Error>{
+        self.runtime.block_on(self.client.execute(compiled_command))
+    }
+    /// Execute `search` and apply its client-side clauses to the response, see `SearchBuilder::post_process`.
+    pub fn search(&self, search : &SearchBuilder) -> Result<DBResponse,Error>{
+        self.runtime.block_on(self.client.search(search))
+    }
+    /// Same as `execute`, but returns the encoded rows instead of decoding them, see `DBResponse::iter_rows`.
+    pub fn execute_raw(&self,compiled_command : CompiledAlba) -> Result<Vec<u8>,Error>{
+        self.runtime.block_on(self.client.execute_raw(compiled_command))
+    }
+    /// Same as `execute`, overriding when the command is retried.
+    pub fn execute_with(&self,compiled_command : CompiledAlba, retry : Retry) -> Result<DBResponse,Error>{
+        self.runtime.block_on(self.client.execute_with(compiled_command, retry))
+    }
+    /// Same as `execute_raw`, overriding when the command is retried.
+    pub fn execute_raw_with(&self,compiled_command : CompiledAlba, retry : Retry) -> Result<Vec<u8>,Error>{
+        self.runtime.block_on(self.client.execute_raw_with(compiled_command, retry))
+    }
+    /// Insert `rows` into a container in chunks, see `BulkInsert`.
+    ///
+    /// The failures of the chunks are in the report, this only fails when the connections of the chunks can't be
+    /// opened or the transaction can't be ended.
+    pub fn bulk_insert<I : IntoIterator<Item = Vec<AlbaTypes>>>(&self, bulk : &BulkInsert, rows : I) -> Result<BulkReport,Error>{
+        self.runtime.block_on(self.client.bulk_insert(bulk, rows))
+    }
+}
+
+impl Client {
+    /// This method creates a builder for creating a search in which can be compiled into `CompiledAlba` later.
+    pub fn build_search() -> SearchBuilder{
+        AsyncClient::build_search()
+    }
+    pub fn build_edit_row() -> EditRowBuilder{
+        AsyncClient::build_edit_row()
+    }
+    pub fn build_delete_row() -> DeleteRowBuilder{
+        AsyncClient::build_delete_row()
+    }
+    pub fn build_delete_container() -> DeleteContainerBuilder{
+        AsyncClient::build_delete_container()
+    }
+    pub fn build_create_row() -> CreateRowBuilder{
+        AsyncClient::build_create_row()
+    }
+    pub fn build_batch_create_row() -> BatchCreateRowsBuilder{
+        AsyncClient::build_batch_create_row()
+    }
+    pub fn build_create_container() -> CreateContainerBuilder{
+        AsyncClient::build_create_container()
+    }
+    pub fn build_commit() -> CommitBuilder{
+        AsyncClient::build_commit()
+    }
+    pub fn build_rollback() -> RollbackBuilder{
+        AsyncClient::build_rollback()
+    }
+    pub fn build_batch() -> BatchBuilder{
+        AsyncClient::build_batch()
+    }
+}
+
+impl Client {
+    /// Start a transaction on `container`, or on every container with `None`.
+    pub fn transaction(&self, container : Option<&str>) -> Transaction<'_>{
+        Transaction{transaction:Some(self.client.transaction(container)),runtime:&self.runtime}
+    }
+    /// Run `f` in a transaction, committed when `f` succeeds and rolled back when it fails.
+    pub fn in_transaction<T, F : FnOnce(&Transaction<'_>) -> Result<T,Error>>(&self, container : Option<&str>, f : F) -> Result<T,Error>{
+        let tx = self.transaction(container);
+        match f(&tx){
+            Ok(v) => {
+                tx.commit()?;
+                Ok(v)
+            },
+            Err(e) => {
+                let _ = tx.rollback();
+                Err(e)
+            }
+        }
+    }
+}
+
+impl Client {
+    /// Dump every row of `container` with its schema to the file at `path`, see `DumpReader`.
+    ///
+    /// The protocol can't describe a container, so its `columns` and their types are given, in container order.
+    /// The rows are fetched by one search and written to `<path>.partial`, renamed to `path` once complete. Returns
+    /// the count of dumped rows.
+    pub fn dump<P : AsRef<Path>, F : FnMut(Progress)>(&self, container : &str, columns : &[(&str,u8)], path : P, progress : F) -> Result<u64,Error>{
+        self.runtime.block_on(self.client.dump(container, columns, path, progress))
+    }
+    /// Recreate the container of the dump at `path` and reload its rows, see `Restore`. Returns the count of
+    /// restored rows, including those restored before resuming.
+    pub fn restore<P : AsRef<Path>, F : FnMut(Progress)>(&self, path : P, restore : &Restore, progress : F) -> Result<u64,Error>{
+        self.runtime.block_on(self.client.restore(path, restore, progress))
+    }
+}
+
+impl Client {
+    /// Apply the pending migrations in version order, each recorded in `MIGRATIONS_CONTAINER` once its steps
+    /// succeeded, see `Migration`. With `dry_run` nothing is executed and the report lists what would be.
+    pub fn migrate(&self, migrations : &Migrations, dry_run : bool) -> Result<MigrationReport,Error>{
+        self.runtime.block_on(self.client.migrate(migrations, dry_run))
+    }
+    /// Which migrations are applied, pending, modified since they were applied or unknown.
+    pub fn migration_status(&self, migrations : &Migrations) -> Result<StatusReport,Error>{
+        self.runtime.block_on(self.client.migration_status(migrations))
+    }
+}
+
+#[cfg(feature="import")]
+impl Client {
+    /// Import CSV or JSON Lines into a container in chunks, see `Importer`.
+    pub fn import<R : std::io::Read>(&self, importer : &Importer, reader : R) -> Result<ImportReport,Error>{
+        self.runtime.block_on(self.client.import(importer, reader))
+    }
+}
+
+pub struct Transaction<'a>{
+    // taken when the transaction is ended
+    transaction : Option<AsyncTransaction<'a>>,
+    runtime : &'a Runtime,
+}
+
+impl Transaction<'_> {
+    pub fn container(&self) -> Option<&str>{
+        self.transaction.as_ref().and_then(AsyncTransaction::container)
+    }
+    pub fn execute(&self,compiled_command : CompiledAlba) -> Result<DBResponse,Error>{
+        DBResponse::from_bytes(&self.execute_raw(compiled_command)?)
+    }
+    pub fn execute_raw(&self,compiled_command : CompiledAlba) -> Result<Vec<u8>,Error>{
+        let transaction = self.transaction.as_ref().expect("a transaction is only ended by value");
+        self.runtime.block_on(transaction.execute_raw(compiled_command))
+    }
+    /// Send `Commit`, ending the transaction.
+    pub fn commit(mut self) -> Result<(),Error>{
+        let transaction = self.transaction.take().expect("a transaction is only ended by value");
+        self.runtime.block_on(transaction.commit())
+    }
+    /// Send `Rollback`, ending the transaction.
+    pub fn rollback(mut self) -> Result<(),Error>{
+        let transaction = self.transaction.take().expect("a transaction is only ended by value");
+        self.runtime.block_on(transaction.rollback())
+    }
+}
+
+impl Drop for Transaction<'_> {
+    fn drop(&mut self){
+        // rolled back before returning, `client_tokio::Transaction` would only spawn the rollback
+        if let Some(transaction) = self.transaction.take(){
+            let _ = self.runtime.block_on(transaction.rollback());
+        }
+    }
+}
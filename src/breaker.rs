@@ -0,0 +1,208 @@
+use std::{sync::{Arc, Mutex, PoisonError}, time::{Duration, Instant}};
+
+use crate::albastream::{Error, ErrorKind};
+
+/// The state of a `CircuitBreaker`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState{
+    /// Requests are sent.
+    Closed,
+    /// Requests fail fast with `ErrorKind::CircuitOpen` until the cool-down ends.
+    Open,
+    /// The cool-down ended and a request is probing the server, the others fail fast meanwhile.
+    HalfOpen,
+}
+
+/// Fails the requests of a client fast while the server can't be reached, see `Client::with_circuit_breaker`.
+///
+/// The circuit opens after a number of consecutive attempts failing with `ErrorKind::Connection` or
+/// `ErrorKind::UnexpectedEof`, errors of the server meaning it is up. Once the cool-down has passed the next request
+/// pings the server, reconnecting when the connection is broken, and is sent if the ping succeeds: the circuit closes
+/// when it gets a reply, otherwise it opens for another cool-down. Cache hits are served while open. Clones share
+/// the state.
+#[derive(Clone)]
+pub struct CircuitBreaker{
+    failure_threshold : u32,
+    cool_down : Duration,
+    state : Arc<Mutex<Circuit>>,
+}
+
+struct Circuit{
+    state : CircuitState,
+    failures : u32,
+    opened_until : Instant,
+    /// The count of probes, telling a `Probing` guard whether its probe is still the running one.
+    probes : u64,
+}
+
+impl Circuit{
+    fn open(&mut self, cool_down : Duration){
+        self.state = CircuitState::Open;
+        self.failures = 0;
+        self.opened_until = Instant::now() + cool_down;
+    }
+    /// The error a request is rejected with now, `None` when it may be sent or may probe the server.
+    fn rejection(&self) -> Option<Error>{
+        match self.state{
+            CircuitState::Closed => None,
+            CircuitState::Open if Instant::now() >= self.opened_until => None,
+            CircuitState::Open => Some(Error::new(ErrorKind::CircuitOpen, &format!(
+                "The circuit breaker is open, the server is probed again in {:.1}s",
+                self.opened_until.saturating_duration_since(Instant::now()).as_secs_f64()
+            ))),
+            CircuitState::HalfOpen => Some(Error::new(ErrorKind::CircuitOpen, "The circuit breaker is probing the server")),
+        }
+    }
+}
+
+/// Whether a request may be sent, see `CircuitBreaker::admit`.
+pub(crate) enum Admission{
+    Allowed,
+    /// Ping the server and report with `CircuitBreaker::probed`, then send the request if it is reachable. The
+    /// guard is held until the reply is recorded.
+    Probe(Probing),
+    Rejected(Error),
+}
+
+/// A running probe: when it is dropped before the reply of the probing request is recorded, e.g. because the
+/// request was cancelled, the circuit opens for another cool-down instead of staying half-open.
+pub(crate) struct Probing{
+    breaker : CircuitBreaker,
+    probe : u64,
+}
+
+impl Drop for Probing{
+    fn drop(&mut self){
+        let mut circuit = self.breaker.lock();
+        if circuit.state == CircuitState::HalfOpen && circuit.probes == self.probe{
+            circuit.open(self.breaker.cool_down);
+        }
+    }
+}
+
+impl Default for CircuitBreaker{
+    /// Opens after 5 consecutive failures, for 30s.
+    fn default() -> Self{
+        CircuitBreaker{failure_threshold:5,cool_down:Duration::from_secs(30),state:Arc::new(Mutex::new(Circuit{state:CircuitState::Closed,failures:0,opened_until:Instant::now(),probes:0}))}
+    }
+}
+
+impl CircuitBreaker{
+    pub fn new() -> Self{
+        Self::default()
+    }
+    /// Consecutive failed attempts opening the circuit, `0` is treated as `1`.
+    pub fn failure_threshold(mut self, failures : u32) -> Self{
+        self.failure_threshold = failures.max(1);
+        self
+    }
+    /// How long the circuit stays open before probing the server.
+    pub fn cool_down(mut self, cool_down : Duration) -> Self{
+        self.cool_down = cool_down;
+        self
+    }
+    pub fn state(&self) -> CircuitState{
+        self.lock().state
+    }
+    fn lock(&self) -> std::sync::MutexGuard<'_,Circuit>{
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+    pub(crate) fn admit(&self) -> Admission{
+        let mut circuit = self.lock();
+        if let Some(e) = circuit.rejection(){
+            return Admission::Rejected(e)
+        }
+        if circuit.state == CircuitState::Closed{
+            return Admission::Allowed
+        }
+        circuit.state = CircuitState::HalfOpen;
+        circuit.probes += 1;
+        Admission::Probe(Probing{breaker:self.clone(),probe:circuit.probes})
+    }
+    /// The error `admit` would reject a request with now, without starting a probe.
+    #[cfg(feature="tower")]
+    pub(crate) fn rejection(&self) -> Option<Error>{
+        self.lock().rejection()
+    }
+    /// The circuit stays half-open until the reply of the probing request is recorded.
+    pub(crate) fn probed(&self, reachable : bool){
+        if !reachable{
+            self.lock().open(self.cool_down);
+        }
+    }
+    pub(crate) fn record<T>(&self, result : &Result<T,Error>){
+        let mut circuit = self.lock();
+        let failed = matches!(result, Err(e) if matches!(e.kind(), ErrorKind::Connection | ErrorKind::UnexpectedEof));
+        match circuit.state{
+            CircuitState::HalfOpen if failed => circuit.open(self.cool_down),
+            CircuitState::HalfOpen => {
+                circuit.state = CircuitState::Closed;
+                circuit.failures = 0;
+            },
+            CircuitState::Closed if failed => {
+                circuit.failures += 1;
+                if circuit.failures >= self.failure_threshold{
+                    circuit.open(self.cool_down);
+                }
+            },
+            CircuitState::Closed => circuit.failures = 0,
+            // a request sent before the circuit opened
+            CircuitState::Open => {},
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn opened() -> CircuitBreaker{
+        let breaker = CircuitBreaker::new().failure_threshold(1).cool_down(Duration::ZERO);
+        breaker.record::<()>(&Err(Error::new(ErrorKind::Connection, "refused")));
+        assert_eq!(breaker.state(), CircuitState::Open);
+        breaker
+    }
+
+    #[test]
+    fn a_dropped_probe_opens_the_circuit(){
+        let breaker = opened();
+        let Admission::Probe(probing) = breaker.admit() else { panic!("the cool-down has passed") };
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        drop(probing);
+        assert_eq!(breaker.state(), CircuitState::Open);
+    }
+
+    #[test]
+    fn a_recorded_probe_closes_the_circuit(){
+        let breaker = opened();
+        let Admission::Probe(probing) = breaker.admit() else { panic!("the cool-down has passed") };
+        breaker.probed(true);
+        breaker.record::<()>(&Ok(()));
+        drop(probing);
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    #[cfg(feature="tower")]
+    fn rejections_leave_the_probe_to_the_next_request(){
+        let breaker = CircuitBreaker::new().failure_threshold(1).cool_down(Duration::from_secs(60));
+        assert!(breaker.rejection().is_none());
+        breaker.record::<()>(&Err(Error::new(ErrorKind::Connection, "refused")));
+        assert_eq!(breaker.rejection().map(|e| e.kind()), Some(ErrorKind::CircuitOpen));
+        let breaker = opened();
+        assert!(breaker.rejection().is_none());
+        assert_eq!(breaker.state(), CircuitState::Open);
+        let Admission::Probe(_probing) = breaker.admit() else { panic!("the cool-down has passed") };
+        assert_eq!(breaker.rejection().map(|e| e.kind()), Some(ErrorKind::CircuitOpen));
+    }
+
+    #[test]
+    fn a_stale_probe_leaves_the_next_one(){
+        let breaker = opened();
+        let Admission::Probe(stale) = breaker.admit() else { panic!("the cool-down has passed") };
+        breaker.probed(false);
+        let Admission::Probe(_running) = breaker.admit() else { panic!("the cool-down has passed") };
+        drop(stale);
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+    }
+}
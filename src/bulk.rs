@@ -0,0 +1,198 @@
+use std::iter::Peekable;
+
+use crate::{albastream::{CompiledAlba, Error, ErrorKind}, commands::{Batch, BatchCreateRows, Compile}, types::AlbaTypes};
+
+const DEFAULT_MAX_ROWS : usize = 10_000;
+const DEFAULT_MAX_BYTES : usize = 4 << 20;
+
+/// A bulk insertion of rows into a container, see `Client::bulk_insert`.
+///
+/// The rows are split into `BatchCreateRows` chunks of at most `max_rows` rows and `max_bytes` encoded bytes (a
+/// row larger than `max_bytes` is sent alone). Up to `concurrency` chunks are in flight at once, each through
+/// its own connection. With `transaction` the chunks are sent one by one on the client connection, committed
+/// together once they all succeeded, or rolled back at the first failure.
+#[derive(Debug, Clone)]
+pub struct BulkInsert{
+    pub(crate) container : String,
+    pub(crate) columns : Vec<String>,
+    max_rows : usize,
+    max_bytes : usize,
+    pub(crate) concurrency : usize,
+    pub(crate) transaction : bool,
+}
+
+impl BulkInsert{
+    pub fn new(container : &str, columns : Vec<String>) -> Self{
+        BulkInsert{
+            container: container.to_string(),
+            columns,
+            max_rows: DEFAULT_MAX_ROWS,
+            max_bytes: DEFAULT_MAX_BYTES,
+            concurrency: 1,
+            transaction: false,
+        }
+    }
+    /// Maximum row count of a chunk, 10 000 by default.
+    pub fn max_rows(mut self, rows : usize) -> Self{
+        self.max_rows = rows.clamp(1, u32::MAX as usize);
+        self
+    }
+    /// Maximum size of a compiled chunk, 4 MiB by default.
+    pub fn max_bytes(mut self, bytes : usize) -> Self{
+        self.max_bytes = bytes;
+        self
+    }
+    /// Maximum number of chunks in flight, 1 by default. Ignored within a transaction.
+    pub fn concurrency(mut self, chunks : usize) -> Self{
+        self.concurrency = chunks.max(1);
+        self
+    }
+    pub fn transaction(mut self, transaction : bool) -> Self{
+        self.transaction = transaction;
+        self
+    }
+    /// Split `rows` into chunks, compiled lazily as the iterator advances.
+    pub fn chunks<I : IntoIterator<Item = Vec<AlbaTypes>>>(&self, rows : I) -> Chunks<'_, I::IntoIter>{
+        let header = 1 + 1 + self.container.len() + 1 + self.columns.iter().map(|c| 1 + c.len()).sum::<usize>() + 4;
+        Chunks{bulk:self,rows:rows.into_iter().peekable(),header,index:0,next_row:0}
+    }
+}
+
+/// Iterator over the chunks of a `BulkInsert`.
+pub struct Chunks<'a, I : Iterator<Item = Vec<AlbaTypes>>>{
+    bulk : &'a BulkInsert,
+    rows : Peekable<I>,
+    header : usize,
+    index : usize,
+    next_row : usize,
+}
+
+impl<I : Iterator<Item = Vec<AlbaTypes>>> Iterator for Chunks<'_, I>{
+    type Item = Chunk;
+    fn next(&mut self) -> Option<Chunk>{
+        let bulk = self.bulk;
+        let first_row = self.next_row;
+        let mut col_val = Vec::new();
+        let mut bytes = self.header;
+        let mut invalid = None;
+        while col_val.len() < bulk.max_rows{
+            let Some(row) = self.rows.peek() else { break };
+            let len = row.iter().map(AlbaTypes::encoded_len).sum::<usize>();
+            if !col_val.is_empty() && bytes + len > bulk.max_bytes{
+                break
+            }
+            if row.len() != bulk.columns.len() && invalid.is_none(){
+                invalid = Some(Error::new(ErrorKind::InvalidInput, &format!("row {} has {} values for {} columns", first_row + col_val.len(), row.len(), bulk.columns.len())));
+            }
+            bytes += len;
+            col_val.extend(self.rows.next());
+        }
+        if col_val.is_empty(){
+            return None
+        }
+        let rows = col_val.len();
+        let compiled = match invalid{
+            Some(e) => Err(e),
+            None => BatchCreateRows{container:bulk.container.clone(),col_nam:bulk.columns.clone(),col_val}.compile(),
+        };
+        let chunk = Chunk{index:self.index,first_row,rows,compiled};
+        self.index += 1;
+        self.next_row += rows;
+        Some(chunk)
+    }
+}
+
+/// A chunk of a `BulkInsert`: the compiled `BatchCreateRows` of rows `first_row..first_row + rows`.
+pub struct Chunk{
+    pub index : usize,
+    pub first_row : usize,
+    pub rows : usize,
+    pub compiled : Result<CompiledAlba,Error>,
+}
+
+impl Chunk{
+    /// Split the chunk into its compiled command and its report, failed if the chunk could not be compiled.
+    pub fn into_parts(self) -> (Option<CompiledAlba>, ChunkReport){
+        let (compiled, bytes, result) = match self.compiled{
+            Ok(c) => {
+                let bytes = c.len();
+                (Some(c), bytes, Ok(()))
+            },
+            Err(e) => (None, 0, Err(e)),
+        };
+        (compiled, ChunkReport{index:self.index,first_row:self.first_row,rows:self.rows,bytes,result})
+    }
+}
+
+/// The outcome of a chunk of a `BulkInsert`.
+#[derive(Debug, Clone)]
+pub struct ChunkReport{
+    pub index : usize,
+    pub first_row : usize,
+    pub rows : usize,
+    pub bytes : usize,
+    pub result : Result<(),Error>,
+}
+
+/// The outcome of a `BulkInsert`, one report per chunk sent in chunk order.
+///
+/// Within a transaction the chunks following the first failure are not sent, and `rolled_back` is set.
+#[derive(Debug, Clone, Default)]
+pub struct BulkReport{
+    pub chunks : Vec<ChunkReport>,
+    pub rolled_back : bool,
+}
+
+impl BulkReport{
+    pub fn is_success(&self) -> bool{
+        !self.rolled_back && self.chunks.iter().all(|c| c.result.is_ok())
+    }
+    /// Rows of the chunks that succeeded, none if the transaction was rolled back.
+    pub fn inserted_rows(&self) -> usize{
+        if self.rolled_back{
+            return 0
+        }
+        self.chunks.iter().filter(|c| c.result.is_ok()).map(|c| c.rows).sum()
+    }
+    pub fn failed(&self) -> impl Iterator<Item = &ChunkReport>{
+        self.chunks.iter().filter(|c| c.result.is_err())
+    }
+}
+
+/// Split a non transactional `Batch` into batches of at most `max_commands` commands and `max_bytes` compiled
+/// bytes (a command larger than `max_bytes` is sent alone). A transactional batch can't be split without losing
+/// its atomicity and is an error.
+pub fn split_batch(batch : Batch, max_commands : usize, max_bytes : usize) -> Result<Vec<CompiledAlba>,Error>{
+    if batch.transaction{
+        return Err(Error::new(ErrorKind::InvalidInput, "A transactional batch can't be split"))
+    }
+    let max_commands = max_commands.clamp(1, i32::MAX as usize);
+    let mut batches = Vec::new();
+    let mut current = Vec::new();
+    let mut bytes = 5;
+    for command in batch.commands{
+        let compiled = command.compile()?;
+        let len = 4 + compiled.len();
+        if !current.is_empty() && (current.len() == max_commands || bytes + len > max_bytes){
+            batches.push(frame_batch(&current));
+            current.clear();
+            bytes = 5;
+        }
+        bytes += len;
+        current.push(compiled);
+    }
+    if !current.is_empty(){
+        batches.push(frame_batch(&current));
+    }
+    Ok(batches)
+}
+
+fn frame_batch(commands : &[CompiledAlba]) -> CompiledAlba{
+    let mut binary = vec![9u8]; // operation id
+    binary.extend_from_slice(&(commands.len() as i32).to_le_bytes());
+    for command in commands{
+        binary.extend_from_slice(&(command.len() as u32).to_le_bytes());
+        binary.extend_from_slice(command);
+    }
+    binary
+}
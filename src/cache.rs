@@ -0,0 +1,238 @@
+use std::{collections::{HashMap, VecDeque}, sync::{Arc, Mutex, PoisonError}, time::{Duration, Instant}};
+
+use crate::{albastream::Error, commands::Commands};
+
+/// A client-side cache of search replies keyed by the compiled search, see `Client::with_cache`.
+///
+/// The writes a client sends (`CreateRow`, `EditRow`, `DeleteRow`, `BatchCreateRows`, `DeleteContainer` and
+/// `Rollback`, also within batches) invalidate the entries of their container, a `Rollback` of every container
+/// clears the cache. Writes of other clients are not seen, `invalidate` and the TTL bound how stale a reply can be.
+/// When the cache is full the oldest entries are evicted first. Clones share the entries.
+#[derive(Clone)]
+pub struct QueryCache{
+    ttl : Duration,
+    max_entries : usize,
+    max_bytes : usize,
+    state : Arc<Mutex<State>>,
+}
+
+/// The counters of a `QueryCache`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats{
+    pub hits : u64,
+    pub misses : u64,
+    /// Entries dropped to make room for new ones.
+    pub evictions : u64,
+    /// Entries dropped by writes and `invalidate`.
+    pub invalidations : u64,
+    pub entries : usize,
+    pub bytes : usize,
+}
+
+impl CacheStats{
+    /// The share of lookups that were hits, 0 before any lookup.
+    pub fn hit_ratio(&self) -> f64{
+        match self.hits + self.misses{
+            0 => 0.0,
+            lookups => self.hits as f64 / lookups as f64,
+        }
+    }
+}
+
+struct Entry{
+    payload : Vec<u8>,
+    container : String,
+    expires : Instant,
+    id : u64,
+}
+
+#[derive(Default)]
+struct State{
+    entries : HashMap<Vec<u8>,Entry>,
+    // insertion order for the eviction, the entries replaced or invalidated since are skipped
+    order : VecDeque<(Vec<u8>,u64)>,
+    // bumped by every invalidation, a reply is only stored if its container wasn't written while it was fetched
+    generations : HashMap<String,u64>,
+    epoch : u64,
+    next_id : u64,
+    stats : CacheStats,
+}
+
+impl State{
+    fn remove(&mut self, key : &[u8]) -> Option<Entry>{
+        let entry = self.entries.remove(key)?;
+        self.stats.bytes -= entry.payload.len();
+        Some(entry)
+    }
+    fn generation(&self, container : &str) -> u64{
+        self.generations.get(container).copied().unwrap_or(0)
+    }
+    fn invalidate(&mut self, container : Option<&str>){
+        let Some(container) = container else {
+            self.epoch += 1;
+            self.stats.invalidations += self.entries.len() as u64;
+            self.entries.clear();
+            self.order.clear();
+            self.stats.bytes = 0;
+            return
+        };
+        *self.generations.entry(container.to_string()).or_insert(0) += 1;
+        let before = self.entries.len();
+        let mut freed = 0;
+        self.entries.retain(|_, entry| {
+            let keep = entry.container != container;
+            if !keep{
+                freed += entry.payload.len();
+            }
+            keep
+        });
+        self.stats.bytes -= freed;
+        self.stats.invalidations += (before - self.entries.len()) as u64;
+    }
+}
+
+impl Default for QueryCache{
+    /// Entries live 30s, up to 1024 entries and 64 MiB of replies.
+    fn default() -> Self{
+        QueryCache{ttl:Duration::from_secs(30),max_entries:1024,max_bytes:64 << 20,state:Arc::new(Mutex::new(State::default()))}
+    }
+}
+
+impl QueryCache{
+    pub fn new() -> Self{
+        Self::default()
+    }
+    /// How long a reply is served from the cache.
+    pub fn ttl(mut self, ttl : Duration) -> Self{
+        self.ttl = ttl;
+        self
+    }
+    /// Maximum number of cached replies, `0` disabling the cache.
+    pub fn max_entries(mut self, max_entries : usize) -> Self{
+        self.max_entries = max_entries;
+        self
+    }
+    /// Maximum total size of the cached replies, a reply larger than this is never cached.
+    pub fn max_bytes(mut self, max_bytes : usize) -> Self{
+        self.max_bytes = max_bytes;
+        self
+    }
+    pub fn stats(&self) -> CacheStats{
+        let state = self.lock();
+        CacheStats{entries:state.entries.len(),..state.stats}
+    }
+    /// Drop the entries of `container`, e.g. after another client wrote to it.
+    pub fn invalidate(&self, container : &str){
+        self.lock().invalidate(Some(container));
+    }
+    /// Drop every entry, the counters are kept.
+    pub fn clear(&self){
+        self.lock().invalidate(None);
+    }
+    fn lock(&self) -> std::sync::MutexGuard<'_,State>{
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// A request as seen by the cache, see `QueryCache::begin`.
+pub(crate) enum Cached{
+    /// The reply of a cached search.
+    Hit(Vec<u8>),
+    /// Send the request, then pass its reply to `QueryCache::finish`.
+    Miss(Ticket),
+}
+
+pub(crate) enum Ticket{
+    Search{key : Vec<u8>, container : String, generation : u64, epoch : u64},
+    /// The containers written, none for every container.
+    Write(Vec<Option<String>>),
+    Other,
+}
+
+impl QueryCache{
+    pub(crate) fn begin(&self, compiled_command : &[u8]) -> Cached{
+        match compiled_command.first(){
+            Some(5) => {},
+            Some(1..=4 | 7..=9) => {
+                let mut written = Vec::new();
+                match Commands::decompile(compiled_command){
+                    Ok(command) => writes(&command, &mut written),
+                    Err(_) => written.push(None),
+                }
+                let mut state = self.lock();
+                for container in written.iter(){
+                    state.invalidate(container.as_deref());
+                }
+                return Cached::Miss(Ticket::Write(written))
+            },
+            _ => return Cached::Miss(Ticket::Other)
+        }
+        let mut state = self.lock();
+        let now = Instant::now();
+        match state.entries.get(compiled_command){
+            Some(entry) if entry.expires > now => {
+                let payload = entry.payload.clone();
+                state.stats.hits += 1;
+                return Cached::Hit(payload)
+            },
+            Some(_) => {
+                state.remove(compiled_command);
+            },
+            None => {},
+        }
+        state.stats.misses += 1;
+        drop(state);
+        let Some(container) = Commands::container_of(compiled_command) else { return Cached::Miss(Ticket::Other) };
+        let state = self.lock();
+        Cached::Miss(Ticket::Search{key:compiled_command.to_vec(),generation:state.generation(&container),epoch:state.epoch,container})
+    }
+    pub(crate) fn finish(&self, ticket : Ticket, result : &Result<Vec<u8>,Error>){
+        match ticket{
+            Ticket::Search{key, container, generation, epoch} => {
+                let Ok(payload) = result else { return };
+                if self.max_entries == 0 || payload.len() > self.max_bytes{
+                    return
+                }
+                let mut state = self.lock();
+                if state.epoch != epoch || state.generation(&container) != generation{
+                    return
+                }
+                state.remove(&key);
+                while state.entries.len() >= self.max_entries || state.stats.bytes + payload.len() > self.max_bytes{
+                    let Some((oldest, id)) = state.order.pop_front() else { break };
+                    if state.entries.get(&oldest).is_some_and(|entry| entry.id == id){
+                        state.remove(&oldest);
+                        state.stats.evictions += 1;
+                    }
+                }
+                let id = state.next_id;
+                state.next_id += 1;
+                if state.order.len() > 2 * state.entries.len() + 64{
+                    let State{order, entries, ..} = &mut *state;
+                    order.retain(|(key, id)| entries.get(key).is_some_and(|entry| entry.id == *id));
+                }
+                state.order.push_back((key.clone(), id));
+                state.stats.bytes += payload.len();
+                state.entries.insert(key, Entry{payload:payload.clone(),container,expires:Instant::now() + self.ttl,id});
+            },
+            // again once written, a search sent meanwhile may have read the container before the write
+            Ticket::Write(written) => {
+                let mut state = self.lock();
+                for container in written.iter(){
+                    state.invalidate(container.as_deref());
+                }
+            },
+            Ticket::Other => {},
+        }
+    }
+}
+
+/// The containers `command` writes to, none for every container.
+fn writes(command : &Commands, written : &mut Vec<Option<String>>){
+    match command{
+        Commands::Batch(batch) => batch.commands.iter().for_each(|command| writes(command, written)),
+        Commands::CreateRow(_) | Commands::EditRow(_) | Commands::DeleteRow(_) | Commands::DeleteContainer(_)
+            | Commands::BatchCreateRows(_) | Commands::Rollback(_) => written.push(command.container().map(str::to_string)),
+        Commands::CreateContainer(_) | Commands::Search(_) | Commands::Commit(_) => {},
+    }
+}
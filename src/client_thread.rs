@@ -1,107 +1,665 @@
 use falcotcp::Client as RawClient;
-use std::sync::{Arc, Mutex};
-use std::{io::Error as IoError, thread, time::Duration};
+use std::sync::{Arc, Weak, Mutex, RwLock, mpsc};
+use std::{io::Error as IoError, path::Path, thread, time::{Duration, Instant}};
 
 use crate::{
     albastream::{CompiledAlba, Error, ErrorKind},
+    breaker::{Admission, CircuitBreaker},
+    bulk::{BulkInsert, BulkReport, Chunk},
+    cache::{Cached, QueryCache},
+    commands::{Commands, Commit, Rollback},
     db_response::DBResponse,
+    dump::{DumpReader, Progress, Restore, dump_search, restore_failed, write_dump},
+    endpoints::{Endpoints, Hosts},
+    intercept::{Interceptor, Interceptors, Outgoing},
+    metrics::{Metrics, Opened},
+    migrate::{AppliedMigration, MigrationReport, Migrations, StatusReport, Step},
+    retry::{Retry, RetryPolicy},
+    secret::Secret,
+    types::AlbaTypes,
     handler::{
         BatchBuilder, BatchCreateRowsBuilder, CommitBuilder, CreateContainerBuilder,
         CreateRowBuilder, DeleteContainerBuilder, DeleteRowBuilder, EditRowBuilder,
         RollbackBuilder, SearchBuilder,
     },
 };
+#[cfg(feature = "import")]
+use crate::import::{ImportReport, Importer};
+#[cfg(feature = "tracing")]
+use crate::instrument;
 pub struct Client {
     connection: Arc<Mutex<RawClient>>,
+    hosts: Arc<Hosts>,
+    replica: Option<(Arc<Mutex<RawClient>>, Arc<Hosts>)>,
+    password: Arc<Secret>,
+    retry_policy: RetryPolicy,
+    metrics: Arc<RwLock<Option<Metrics>>>,
+    interceptors: Interceptors,
+    cache: Option<QueryCache>,
+    breaker: Option<CircuitBreaker>,
+    opened: Option<Opened>,
+    #[cfg(feature = "tracing")]
+    traced_values: bool,
 }
 impl Client {
-    pub fn connect(host: &str, password: [u8; 32]) -> Result<Client, IoError> {
-        let c = Arc::new(Mutex::new(RawClient::new(host, password)?));
-        let cb = c.clone();
-        thread::spawn(async move || {
+    pub fn connect<S: Into<Secret>>(host: &str, password: S) -> Result<Client, IoError> {
+        Client::connect_endpoints(&Endpoints::new(host), password)
+    }
+    /// Connect to a primary with standbys and replicas, see `Endpoints`. Fails when none of the primary and the
+    /// standbys, or none of the replicas, can be reached.
+    pub fn connect_endpoints<S: Into<Secret>>(endpoints: &Endpoints, password: S) -> Result<Client, IoError> {
+        let password = Arc::new(password.into());
+        let hosts = Arc::new(Hosts::new(endpoints.primary()));
+        let c = Arc::new(Mutex::new(open(&hosts, &password)?));
+        let replica = match endpoints.replicas() {
+            [] => None,
+            replicas => {
+                let replica_hosts = Arc::new(Hosts::new(replicas));
+                Some((Arc::new(Mutex::new(open(&replica_hosts, &password)?)), replica_hosts))
+            }
+        };
+        let metrics = Arc::new(RwLock::new(None::<Metrics>));
+        let mut heartbeats = vec![(c.clone(), hosts.clone())];
+        heartbeats.extend(replica.clone());
+        // the heartbeat only holds weak references, so it ends with the last clone of the client and doesn't keep the
+        // connections or the secret alive
+        let heartbeats: Vec<(Weak<Mutex<RawClient>>, Weak<Hosts>)> = heartbeats.iter().map(|(c, h)| (Arc::downgrade(c), Arc::downgrade(h))).collect();
+        let (heartbeat_metrics, heartbeat_password) = (Arc::downgrade(&metrics), Arc::downgrade(&password));
+        thread::spawn(move || {
             loop {
                 thread::sleep(Duration::from_secs(15));
-                let _ = cb.lock().unwrap().ping();
+                let (Some(password), Some(metrics)) = (heartbeat_password.upgrade(), heartbeat_metrics.upgrade()) else { return };
+                for (connection, hosts) in heartbeats.iter() {
+                    let (Some(connection), Some(hosts)) = (connection.upgrade(), hosts.upgrade()) else { return };
+                    heartbeat(&connection, &hosts, &password, &metrics);
+                }
             }
         });
-        Ok(Client { connection: c })
+        Ok(Client {
+            connection: c,
+            hosts,
+            replica,
+            password,
+            retry_policy: RetryPolicy::default(),
+            metrics,
+            interceptors: Interceptors::default(),
+            cache: None,
+            breaker: None,
+            opened: None,
+            #[cfg(feature = "tracing")]
+            traced_values: false,
+        })
+    }
+    /// Report the requests, reconnections, heartbeats and connections of the client to `metrics`.
+    /// The connection of the client is counted as open until the client is dropped.
+    pub fn with_metrics(mut self, metrics: Metrics) -> Client {
+        self.opened = Some(metrics.opened(1));
+        *self.metrics.write().unwrap() = Some(metrics);
+        self
+    }
+    /// Register an interceptor, running around every request after the ones registered before, see `Interceptor`.
+    pub fn with_interceptor<I: Interceptor + 'static>(mut self, interceptor: I) -> Client {
+        self.interceptors.push(Arc::new(interceptor));
+        self
+    }
+    /// Serve repeated searches from `cache`, see `QueryCache`.
+    pub fn with_cache(mut self, cache: QueryCache) -> Client {
+        self.cache = Some(cache);
+        self
+    }
+    pub fn cache(&self) -> Option<&QueryCache> {
+        self.cache.as_ref()
+    }
+    /// Fail fast with `ErrorKind::CircuitOpen` while the server can't be reached, see `CircuitBreaker`.
+    pub fn with_circuit_breaker(mut self, breaker: CircuitBreaker) -> Client {
+        self.breaker = Some(breaker);
+        self
+    }
+    pub fn circuit_breaker(&self) -> Option<&CircuitBreaker> {
+        self.breaker.as_ref()
+    }
+    /// Record the values of the commands in the `tytodb.execute` spans, they are left out by default since they
+    /// may hold personal data or secrets.
+    #[cfg(feature = "tracing")]
+    pub fn with_traced_values(mut self, traced: bool) -> Client {
+        self.traced_values = traced;
+        self
+    }
+    /// Replace the retry policy, `RetryPolicy::default()` unless set.
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Client {
+        self.retry_policy = policy;
+        self
+    }
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
     }
     pub fn execute(&self, compiled_command: CompiledAlba) -> Result<DBResponse, Error> {
-        let mut client = self.connection.lock().unwrap();
-        let b = match client.message(compiled_command) {
-            Ok(a) => a,
-            Err(e) => return Err(Error::new(ErrorKind::InvalidInput, e.to_string().as_str())),
+        DBResponse::from_bytes(&self.execute_raw(compiled_command)?)
+    }
+    /// Execute `search` and apply its client-side clauses to the response, see `SearchBuilder::post_process`.
+    pub fn search(&self, search: &SearchBuilder) -> Result<DBResponse, Error> {
+        search.post_process(self.execute(search.request()?)?)
+    }
+    /// Same as `execute`, but returns the encoded rows instead of decoding them, see `DBResponse::iter_rows`.
+    pub fn execute_raw(&self, compiled_command: CompiledAlba) -> Result<Vec<u8>, Error> {
+        self.execute_raw_with(compiled_command, Retry::Auto)
+    }
+    /// Same as `execute`, overriding when the command is retried.
+    pub fn execute_with(&self, compiled_command: CompiledAlba, retry: Retry) -> Result<DBResponse, Error> {
+        DBResponse::from_bytes(&self.execute_raw_with(compiled_command, retry)?)
+    }
+    /// Same as `execute_raw`, overriding when the command is retried.
+    pub fn execute_raw_with(&self, compiled_command: CompiledAlba, retry: Retry) -> Result<Vec<u8>, Error> {
+        self.execute_routed(compiled_command, retry, true)
+    }
+    /// `execute_raw_with`, searches going to the replicas when `replica_reads`.
+    fn execute_routed(&self, compiled_command: CompiledAlba, retry: Retry, replica_reads: bool) -> Result<Vec<u8>, Error> {
+        let (compiled_command, command) = match self.interceptors.before(compiled_command) {
+            Outgoing::Send(compiled, command) => (compiled, command),
+            Outgoing::Reply(reply) => return reply,
         };
-        if b[0] == 1u8 {
-            return Err(Error::new(
-                ErrorKind::Other,
-                String::from_utf8_lossy(&b[1..]).to_string().as_str(),
-            ));
+        let ticket = match self.cache.as_ref().map(|cache| cache.begin(&compiled_command)) {
+            Some(Cached::Hit(payload)) => return self.interceptors.after(command, Ok(payload)),
+            Some(Cached::Miss(ticket)) => Some(ticket),
+            None => None,
+        };
+        #[cfg(feature = "tracing")]
+        let (span, started) = (instrument::execute_span(&compiled_command, self.traced_values), Instant::now());
+        let result = {
+            #[cfg(feature = "tracing")]
+            let _entered = span.enter();
+            self.send_with_retries(compiled_command, retry, replica_reads)
+        };
+        #[cfg(feature = "tracing")]
+        instrument::record_reply(&span, &result, started.elapsed());
+        if let (Some(cache), Some(ticket)) = (&self.cache, ticket) {
+            cache.finish(ticket, &result);
+        }
+        self.interceptors.after(command, result)
+    }
+    fn send_with_retries(&self, compiled_command: CompiledAlba, retry: Retry, replica_reads: bool) -> Result<Vec<u8>, Error> {
+        let mut attempt = 1;
+        loop {
+            match self.send_guarded(compiled_command.clone(), replica_reads) {
+                Err(e) if self.retry_policy.should_retry(retry, &compiled_command, attempt, &e) => {
+                    let delay = self.retry_policy.delay(attempt);
+                    #[cfg(feature = "tracing")]
+                    instrument::retrying(&e, attempt, delay);
+                    thread::sleep(delay);
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+    /// `send` through the circuit breaker.
+    fn send_guarded(&self, compiled_command: CompiledAlba, replica_reads: bool) -> Result<Vec<u8>, Error> {
+        let Some(breaker) = &self.breaker else {
+            return self.send(compiled_command, replica_reads);
+        };
+        let (probing, rejected) = match breaker.admit() {
+            Admission::Allowed => (None, None),
+            Admission::Probe(probing) => {
+                let reachable = probe(&mut self.connection.lock().unwrap(), &self.hosts, &self.password);
+                breaker.probed(reachable);
+                (Some(probing), (!reachable).then(|| Error::new(ErrorKind::CircuitOpen, "The circuit breaker probed the server, it can't be reached")))
+            }
+            Admission::Rejected(e) => (None, Some(e)),
+        };
+        if let Some(e) = rejected {
+            if let Some(metrics) = self.metrics.read().unwrap().as_ref() {
+                metrics.rejected(&compiled_command, &e);
+            }
+            return Err(e);
+        }
+        let result = self.send(compiled_command, replica_reads);
+        breaker.record(&result);
+        drop(probing);
+        result
+    }
+    /// The replica serving `compiled_command`, when it is a search and `replica_reads`.
+    fn replica_for(&self, compiled_command: &[u8], replica_reads: bool) -> Option<&(Arc<Mutex<RawClient>>, Arc<Hosts>)> {
+        self.replica.as_ref().filter(|_| replica_reads && compiled_command.first() == Some(&5))
+    }
+    fn send(&self, compiled_command: CompiledAlba, replica_reads: bool) -> Result<Vec<u8>, Error> {
+        let metrics = self.metrics.read().unwrap();
+        if let Some((replica, hosts)) = self.replica_for(&compiled_command, replica_reads) {
+            match exchange(&mut replica.lock().unwrap(), hosts, &self.password, compiled_command.clone(), metrics.as_ref()) {
+                // the replicas can't be reached, the primary serves the search meanwhile
+                Err(e) if e.kind() == ErrorKind::Connection => {}
+                result => return result,
+            }
+        }
+        exchange(&mut self.connection.lock().unwrap(), &self.hosts, &self.password, compiled_command, metrics.as_ref())
+    }
+    /// Insert `rows` into a container in chunks, see `BulkInsert`.
+    ///
+    /// The failures of the chunks are in the report, this only fails when the connections of the chunks can't be
+    /// opened or the transaction can't be ended.
+    pub fn bulk_insert<I: IntoIterator<Item = Vec<AlbaTypes>>>(&self, bulk: &BulkInsert, rows: I) -> Result<BulkReport, Error> {
+        let mut report = BulkReport::default();
+        if bulk.transaction {
+            let tx = self.transaction(Some(&bulk.container));
+            for chunk in bulk.chunks(rows) {
+                let (compiled, mut chunk_report) = chunk.into_parts();
+                if let Some(compiled) = compiled {
+                    chunk_report.result = tx.execute_raw(compiled).map(|_| ());
+                }
+                let failed = chunk_report.result.is_err();
+                report.chunks.push(chunk_report);
+                if failed {
+                    tx.rollback()?;
+                    report.rolled_back = true;
+                    return Ok(report);
+                }
+            }
+            tx.commit()?;
+            return Ok(report);
+        }
+        let mut connections = vec![self.connection.clone()];
+        for _ in 1..bulk.concurrency {
+            let raw = RawClient::new(self.hosts.host(self.hosts.current()), *self.password.expose()).map_err(|e| Error::new(ErrorKind::Connection, e.to_string().as_str()))?;
+            connections.push(Arc::new(Mutex::new(raw)));
+        }
+        let metrics = self.metrics.read().unwrap();
+        let metrics = metrics.as_ref();
+        let _opened = metrics.map(|metrics| metrics.opened(connections.len() - 1));
+        if let Some(cache) = &self.cache {
+            cache.invalidate(&bulk.container);
+        }
+        let (sender, receiver) = mpsc::sync_channel::<Chunk>(bulk.concurrency);
+        let receiver = Mutex::new(receiver);
+        thread::scope(|scope| {
+            let workers: Vec<_> = connections
+                .iter()
+                .enumerate()
+                .map(|(index, connection)| {
+                    let receiver = &receiver;
+                    // the other connections fail over without moving the host of the connection of the client
+                    let cursor = (index > 0).then(|| self.hosts.cursor());
+                    scope.spawn(move || {
+                        let hosts = cursor.as_ref().unwrap_or(&self.hosts);
+                        let mut reports = Vec::new();
+                        loop {
+                            // the receiver is only locked while waiting, not while the chunk is sent
+                            let next = receiver.lock().unwrap().recv();
+                            let Ok(chunk) = next else { break };
+                            let (compiled, mut chunk_report) = chunk.into_parts();
+                            if let Some(compiled) = compiled {
+                                chunk_report.result = match self.interceptors.before(compiled) {
+                                    Outgoing::Send(compiled, command) => {
+                                        let result = exchange(&mut connection.lock().unwrap(), hosts, &self.password, compiled, metrics);
+                                        self.interceptors.after(command, result)
+                                    }
+                                    Outgoing::Reply(reply) => reply,
+                                }
+                                .map(|_| ());
+                            }
+                            reports.push(chunk_report);
+                        }
+                        reports
+                    })
+                })
+                .collect();
+            for chunk in bulk.chunks(rows) {
+                if sender.send(chunk).is_err() {
+                    break;
+                }
+            }
+            drop(sender);
+            for worker in workers {
+                match worker.join() {
+                    Ok(reports) => report.chunks.extend(reports),
+                    Err(panic) => std::panic::resume_unwind(panic),
+                }
+            }
+        });
+        if let Some(cache) = &self.cache {
+            cache.invalidate(&bulk.container);
+        }
+        report.chunks.sort_by_key(|c| c.index);
+        Ok(report)
+    }
+}
+
+/// Connect to the first of `hosts` accepting the connection, see `Hosts::candidates`.
+fn open(hosts: &Hosts, password: &Secret) -> Result<RawClient, IoError> {
+    let mut failure = None;
+    for index in hosts.candidates() {
+        #[cfg(feature = "tracing")]
+        let (span, started) = (instrument::connect_span(hosts.host(index)), Instant::now());
+        let raw = RawClient::new(hosts.host(index), *password.expose());
+        #[cfg(feature = "tracing")]
+        instrument::record_connect(&span, &raw, started.elapsed());
+        match raw {
+            Ok(raw) => {
+                hosts.connected(index);
+                return Ok(raw);
+            }
+            Err(e) => {
+                hosts.checked(index, false);
+                failure = Some(e);
+            }
+        }
+    }
+    Err(failure.expect("a client has at least one host"))
+}
+
+/// Ping `connection`, failing over when it doesn't answer, then check the other hosts.
+fn heartbeat(connection: &Mutex<RawClient>, hosts: &Hosts, password: &Secret, metrics: &RwLock<Option<Metrics>>) {
+    let host = hosts.host(hosts.current());
+    #[cfg(feature = "tracing")]
+    let span = instrument::heartbeat_span(host);
+    let started = Instant::now();
+    let pinged = connection.lock().unwrap().ping();
+    #[cfg(feature = "tracing")]
+    instrument::record_heartbeat(&span, &pinged, started.elapsed());
+    if let Some(metrics) = metrics.read().unwrap().as_ref() {
+        metrics.heartbeat(host, started.elapsed(), pinged.is_ok());
+    }
+    if pinged.is_err() {
+        hosts.checked(hosts.current(), false);
+        let fresh = open(hosts, password);
+        #[cfg(feature = "tracing")]
+        instrument::reconnected(hosts.host(hosts.current()), &fresh);
+        if let Some(metrics) = metrics.read().unwrap().as_ref() {
+            metrics.reconnected(hosts.host(hosts.current()), fresh.is_ok());
+        }
+        if let Ok(fresh) = fresh {
+            *connection.lock().unwrap() = fresh;
+            hosts.replaced();
         }
-        Ok(DBResponse::from_bytes(&b[1..])?)
+    }
+    for index in hosts.others() {
+        hosts.checked(index, RawClient::new(hosts.host(index), *password.expose()).and_then(|mut checked| checked.ping()).is_ok());
     }
 }
+
+/// Ping `connection` for the circuit breaker, reconnecting when it is broken.
+fn probe(connection: &mut RawClient, hosts: &Hosts, password: &Secret) -> bool {
+    if connection.ping().is_ok() {
+        return true;
+    }
+    match open(hosts, password).and_then(|mut fresh| fresh.ping().map(|_| fresh)) {
+        Ok(fresh) => {
+            *connection = fresh;
+            hosts.replaced();
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+/// Send `compiled_command` through `client` and read the reply, reporting the exchange to `metrics`.
+fn exchange(client: &mut RawClient, hosts: &Hosts, password: &Secret, compiled_command: CompiledAlba, metrics: Option<&Metrics>) -> Result<Vec<u8>, Error> {
+    let started = metrics.map(|m| m.exchange_started(&compiled_command));
+    let result = round_trip(client, hosts, password, compiled_command, metrics);
+    if let (Some(metrics), Some(started)) = (metrics, started) {
+        metrics.exchange_ended(started, &result);
+    }
+    result
+}
+
+fn round_trip(client: &mut RawClient, hosts: &Hosts, password: &Secret, compiled_command: CompiledAlba, metrics: Option<&Metrics>) -> Result<Vec<u8>, Error> {
+    let mut b = match client.message(compiled_command) {
+        Ok(a) => a,
+        Err(e) => {
+            // the stream is unusable after a failed exchange, the next request goes through a new connection
+            let fresh = open(hosts, password);
+            let host = hosts.host(hosts.current());
+            #[cfg(feature = "tracing")]
+            instrument::reconnected(host, &fresh);
+            if let Some(metrics) = metrics {
+                metrics.reconnected(host, fresh.is_ok());
+            }
+            if let Ok(fresh) = fresh {
+                *client = fresh;
+                hosts.replaced();
+            }
+            return Err(Error::new(ErrorKind::Connection, e.to_string().as_str()));
+        }
+    };
+    if b.is_empty() {
+        return Err(Error::new(ErrorKind::UnexpectedEof, "Empty response"));
+    }
+    if b[0] == 1u8 {
+        return Err(Error::new(
+            ErrorKind::Other,
+            String::from_utf8_lossy(&b[1..]).to_string().as_str(),
+        ));
+    }
+    Ok(b.split_off(1))
+}
 impl Client {
     /// This method creates a builder for creating a search in which can be compiled into `CompiledAlba` later.
     pub fn build_search() -> SearchBuilder {
-        return SearchBuilder {
-            container: String::new(),
-            column_names: Vec::new(),
-            conditions: (Vec::new(), Vec::new()),
-        };
+        SearchBuilder::new()
     }
 
     pub fn build_edit_row() -> EditRowBuilder {
-        return EditRowBuilder {
-            container: String::new(),
-            changes: (Vec::new(), Vec::new()),
-            conditions: (Vec::new(), Vec::new()),
-        };
+        EditRowBuilder::new()
     }
 
     pub fn build_delete_row() -> DeleteRowBuilder {
-        return DeleteRowBuilder {
-            container: String::new(),
-            conditions: (Vec::new(), Vec::new()),
-        };
+        DeleteRowBuilder::new()
     }
 
     pub fn build_delete_container() -> DeleteContainerBuilder {
-        return DeleteContainerBuilder {
+        DeleteContainerBuilder {
             container: String::new(),
-        };
+        }
     }
 
     pub fn build_create_row() -> CreateRowBuilder {
-        return CreateRowBuilder {
+        CreateRowBuilder {
             container: String::new(),
             value: (Vec::new(), Vec::new()),
-        };
+        }
     }
 
     pub fn build_batch_create_row() -> BatchCreateRowsBuilder {
-        return BatchCreateRowsBuilder {
+        BatchCreateRowsBuilder {
             container: String::new(),
             value: (Vec::new(), Vec::new()),
-        };
+        }
     }
 
     pub fn build_create_container() -> CreateContainerBuilder {
-        return CreateContainerBuilder {
+        CreateContainerBuilder {
             container: String::new(),
             headers: (Vec::new(), Vec::new()),
-        };
+        }
     }
 
     pub fn build_commit() -> CommitBuilder {
-        return CommitBuilder { container: None };
+        CommitBuilder { container: None }
     }
 
     pub fn build_rollback() -> RollbackBuilder {
-        return RollbackBuilder { container: None };
+        RollbackBuilder { container: None }
     }
 
     pub fn build_batch() -> BatchBuilder {
-        return BatchBuilder::new();
+        BatchBuilder::new()
+    }
+}
+
+impl Client {
+    /// Start a transaction on `container`, or on every container with `None`.
+    pub fn transaction(&self, container: Option<&str>) -> Transaction<'_> {
+        Transaction { client: self, container: container.map(|c| c.to_string()), finished: false, generation: self.hosts.generation() }
+    }
+    /// Run `f` in a transaction, committed when `f` succeeds and rolled back when it fails.
+    pub fn in_transaction<T, F: FnOnce(&Transaction<'_>) -> Result<T, Error>>(&self, container: Option<&str>, f: F) -> Result<T, Error> {
+        let tx = self.transaction(container);
+        match f(&tx) {
+            Ok(v) => {
+                tx.commit()?;
+                Ok(v)
+            }
+            Err(e) => {
+                let _ = tx.rollback();
+                Err(e)
+            }
+        }
+    }
+}
+
+impl Client {
+    /// Dump every row of `container` with its schema to the file at `path`, see `DumpReader`.
+    ///
+    /// The protocol can't describe a container, so its `columns` and their types are given, in container order.
+    /// The rows are fetched by one search and written to `<path>.partial`, renamed to `path` once complete. Returns
+    /// the count of dumped rows. A dump is not resumable: the protocol can't page a search, a failed dump starts over.
+    pub fn dump<P: AsRef<Path>, F: FnMut(Progress)>(&self, container: &str, columns: &[(&str, u8)], path: P, progress: F) -> Result<u64, Error> {
+        let payload = self.execute_raw(dump_search(container, columns)?)?;
+        write_dump(path.as_ref(), container, columns, &payload, progress)
+    }
+    /// Recreate the container of the dump at `path` and reload its rows, see `Restore`. Returns the count of
+    /// restored rows, including those restored before resuming.
+    pub fn restore<P: AsRef<Path>, F: FnMut(Progress)>(&self, path: P, restore: &Restore, mut progress: F) -> Result<u64, Error> {
+        let mut reader = DumpReader::open(path)?;
+        let (create, bulk, mut restored) = restore.prepare(&mut reader)?;
+        if let Some(create) = create {
+            self.execute_raw(create.finish()?)?;
+        }
+        let total = reader.header().rows;
+        for chunk in bulk.chunks(reader.by_ref()) {
+            let rows = chunk.rows as u64;
+            if let Err(e) = chunk.compiled.and_then(|c| self.execute_raw(c)) {
+                return Err(restore_failed(&e, restored));
+            }
+            restored += rows;
+            progress(Progress { rows: restored, total });
+        }
+        match reader.error() {
+            Some(e) => Err(restore_failed(e, restored)),
+            None => Ok(restored),
+        }
+    }
+}
+
+impl Client {
+    /// Apply the pending migrations in version order, each recorded in `MIGRATIONS_CONTAINER` once its steps
+    /// succeeded, see `Migration`. With `dry_run` nothing is executed and the report lists what would be.
+    pub fn migrate(&self, migrations: &Migrations, dry_run: bool) -> Result<MigrationReport, Error> {
+        let applied = self.applied_migrations(!dry_run)?;
+        let mut report = MigrationReport { dry_run, applied: Vec::new() };
+        for migration in migrations.pending(&applied)? {
+            if !dry_run {
+                for (i, step) in migration.steps().iter().enumerate() {
+                    self.apply_step(step).map_err(|e| migration.step_failed(i, &e))?;
+                }
+                self.execute_raw(migration.record()?)?;
+            }
+            report.push(migration);
+        }
+        Ok(report)
+    }
+    /// Which migrations are applied, pending, modified since they were applied or unknown.
+    pub fn migration_status(&self, migrations: &Migrations) -> Result<StatusReport, Error> {
+        migrations.status(&self.applied_migrations(false)?)
+    }
+    fn applied_migrations(&self, create: bool) -> Result<Vec<AppliedMigration>, Error> {
+        match self.execute_raw(Migrations::search_applied()?) {
+            Ok(payload) => Migrations::applied(&payload),
+            // the server can't be asked whether a container exists, only its error for a missing one is taken as such
+            Err(e) if Migrations::is_missing_bookkeeping(&e) => {
+                if create {
+                    self.execute_raw(Migrations::create_bookkeeping()?).map_err(|_| e)?;
+                }
+                Ok(Vec::new())
+            }
+            Err(e) => Err(e),
+        }
+    }
+    fn apply_step(&self, step: &Step) -> Result<(), Error> {
+        if let Some(compiled) = step.compile()? {
+            return self.execute_raw(compiled).map(|_| ());
+        }
+        if let Step::CopyTransform(copy) = step {
+            let rows = copy.rows(&self.execute_raw(copy.search()?)?)?;
+            self.execute_raw(copy.create_target()?)?;
+            for chunk in copy.insertions().chunks(rows) {
+                self.execute_raw(chunk.compiled?)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "import")]
+impl Client {
+    /// Import CSV or JSON Lines into a container in chunks, see `Importer`.
+    pub fn import<R: std::io::Read>(&self, importer: &Importer, reader: R) -> Result<ImportReport, Error> {
+        let mut records = importer.records(reader)?;
+        let bulk = importer.bulk_insert(&records);
+        let chunks = self.bulk_insert(&bulk, &mut records)?;
+        Ok(records.finish(chunks))
+    }
+}
+
+/// A transaction guard, see `Client::transaction`.
+///
+/// The operations are sent as they are executed. `commit` sends a `Commit` for the container, dropping the guard
+/// without committing sends a `Rollback`. The transaction lives on the connection, so the operations executed
+/// directly on the client meanwhile are part of it too. Operations of the guard are never retried, since a retry
+/// may go through a new connection, outside of the transaction. Its searches go to the primary, not to the replicas.
+///
+/// Once the connection is replaced (after a failed exchange, ping or circuit breaker probe) the server has dropped
+/// the transaction with the old one: the operations and `commit` of the guard fail with a `Connection` error
+/// instead of running outside of it, and `rollback` and dropping the guard send nothing.
+pub struct Transaction<'a> {
+    client: &'a Client,
+    container: Option<String>,
+    finished: bool,
+    generation: u64,
+}
+
+impl Transaction<'_> {
+    pub fn container(&self) -> Option<&str> {
+        self.container.as_deref()
+    }
+    pub fn execute(&self, compiled_command: CompiledAlba) -> Result<DBResponse, Error> {
+        DBResponse::from_bytes(&self.execute_raw(compiled_command)?)
+    }
+    pub fn execute_raw(&self, compiled_command: CompiledAlba) -> Result<Vec<u8>, Error> {
+        self.client.hosts.check_session(self.generation)?;
+        let result = self.client.execute_routed(compiled_command, Retry::Never, false);
+        // a reconnection while it was sent means it may have run outside of the transaction
+        self.client.hosts.check_session(self.generation)?;
+        result
+    }
+    /// Send `Commit`, ending the transaction.
+    pub fn commit(mut self) -> Result<(), Error> {
+        self.finished = true;
+        self.client.hosts.check_session(self.generation)?;
+        self.client.execute_raw_with(Commands::Commit(Commit { container: self.container.take() }).compile()?, Retry::Never)?;
+        self.client.hosts.check_session(self.generation)
+    }
+    /// Send `Rollback`, ending the transaction.
+    pub fn rollback(mut self) -> Result<(), Error> {
+        self.finished = true;
+        if self.client.hosts.check_session(self.generation).is_err() {
+            return Ok(());
+        }
+        self.client.execute_raw_with(Commands::Rollback(Rollback { container: self.container.take() }).compile()?, Retry::Never)?;
+        Ok(())
+    }
+}
+
+impl Drop for Transaction<'_> {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        #[cfg(feature = "tracing")]
+        instrument::transaction_dropped(self.container.as_deref());
+        if self.client.hosts.check_session(self.generation).is_err() {
+            return;
+        }
+        if let Ok(rollback) = Commands::Rollback(Rollback { container: self.container.take() }).compile() {
+            let _ = self.client.execute_raw_with(rollback, Retry::Never);
+        }
     }
 }
@@ -1,4 +1,6 @@
-use crate::{albastream::{Error, ErrorKind}, dynamic_int::DynamicInteger, logical_operators::LogicalOperator, types::AlbaTypes};
+use std::fmt;
+
+use crate::{albastream::{Error, ErrorKind}, dynamic_int::DynamicInteger, logical_operators::LogicalOperator, parser::{literal, quote_name}, types::{AlbaTypes, column_type_name}};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Commands{
@@ -44,8 +46,66 @@ impl Commands{
             Commands::Batch(_) => 9,
         }
     }
+    /// The name of a command id (see `id`), e.g. `"search"` for 5, `"unknown"` for an id that is no command.
+    pub fn kind_name(id : u8) -> &'static str{
+        match id{
+            0 => "create_container",
+            1 => "create_row",
+            2 => "edit_row",
+            3 => "delete_row",
+            4 => "delete_container",
+            5 => "search",
+            6 => "commit",
+            7 => "rollback",
+            8 => "batch_create_rows",
+            9 => "batch",
+            _ => "unknown",
+        }
+    }
+    /// The container the command works on, none for batches and for commits and rollbacks of every container.
+    pub fn container(&self) -> Option<&str>{
+        match self{
+            Commands::CreateContainer(c) => Some(&c.name),
+            Commands::CreateRow(c) => Some(&c.container),
+            Commands::EditRow(c) => Some(&c.container),
+            Commands::DeleteRow(c) => Some(&c.container),
+            Commands::DeleteContainer(c) => Some(&c.container),
+            Commands::Search(c) => Some(&c.container),
+            Commands::Commit(c) => c.container.as_deref(),
+            Commands::Rollback(c) => c.container.as_deref(),
+            Commands::BatchCreateRows(c) => Some(&c.container),
+            Commands::Batch(_) => None,
+        }
+    }
+    /// The container of a compiled command, see `container`. The container leading a `BatchCreateRows` is read
+    /// without decoding its rows.
+    pub fn container_of(compiled_command : &[u8]) -> Option<String>{
+        match compiled_command.first(){
+            Some(8) if compiled_command.len() > 1 => {
+                let end = (2 + compiled_command[1] as usize).min(compiled_command.len());
+                Some(String::from_utf8_lossy(&compiled_command[2..end]).into_owned())
+            },
+            _ => Commands::decompile(compiled_command).ok()?.container().map(str::to_string),
+        }
+    }
+    /// Whether executing the command twice leaves the database as executing it once, so it can be retried safely
+    /// when its reply is lost. Creations are not: a retried `CreateRow` may insert the row twice. Neither are `Commit`
+    /// and `Rollback`: the transaction lives on the connection, a retry through a new one would end nothing.
+    pub fn is_idempotent(&self) -> bool{
+        match self{
+            Commands::CreateContainer(_) | Commands::CreateRow(_) | Commands::BatchCreateRows(_) => false,
+            Commands::Commit(_) | Commands::Rollback(_) => false,
+            Commands::EditRow(_) | Commands::DeleteRow(_) | Commands::DeleteContainer(_) | Commands::Search(_) => true,
+            Commands::Batch(batch) => batch.commands.iter().all(Commands::is_idempotent),
+        }
+    }
 }
 
+/// A single `(column, operator, value)` condition of a condition chain.
+pub type Condition = (String,LogicalOperator,AlbaTypes);
+/// A condition chain: the conditions plus the `(index, 'a'|'o')` logic gates joining them.
+pub type Conditions<I = u8> = (Vec<Condition>,Vec<(I,char)>);
+
 pub trait Compile {
     fn compile(&self) -> Result<Vec<u8>,Error>;    
 }
@@ -299,6 +359,9 @@ impl Commands{
                     offset += 4;
                     u32::from_le_bytes(load)
                 } ;
+                if col_count == 0 && batched_count > 0 {
+                    return Err(Error::new(ErrorKind::InvalidInput, "A BatchCreateRows without columns can't hold rows"));
+                }
                 for _ in 0..batched_count{ 
                     let mut col_val = Vec::new();
                     for _ in 0..col_count {
@@ -346,12 +409,12 @@ impl Compile for CreateContainer {
         binary.extend_from_slice(&0u8.to_le_bytes());
 
         binary.extend_from_slice(&(self.name.len() as u8).to_le_bytes());
-        binary.extend_from_slice(&self.name.as_bytes());
+        binary.extend_from_slice(self.name.as_bytes());
 
         binary.extend_from_slice(&(self.col_nam.len() as u8).to_le_bytes());
         for i in &self.col_nam{
             binary.extend_from_slice(&(i.len() as u8).to_le_bytes());
-            binary.extend_from_slice(&i.as_bytes());
+            binary.extend_from_slice(i.as_bytes());
         }
 
         for i in &self.col_val{
@@ -422,7 +485,7 @@ impl Compile for EditRow{
         }
         binary.push(self.conditions.1.len() as u8);
         for i in self.conditions.1.iter(){
-            binary.push(i.0 as u8);
+            binary.push(i.0);
             binary.push(match i.1{
                 'A'|'a' => 1,
                 'O'|'o' => 0,
@@ -437,11 +500,10 @@ impl Compile for DeleteRow{
         if self.container.len() > MAX_CONTAINER_NAME_LENGTH{
             return Err(Error::new(ErrorKind::InvalidInput,"Invalid container name, the maximum length of a container name is 100 and the entered exceeded the value."))
         }
-        if let Some(c) = &self.conditions{
-            if c.0.len() > u8::MAX as usize{
-                return Err(Error::new(ErrorKind::InvalidInput, "The condition count exceed the limit of 255"))
-            }
-        } 
+        if let Some(c) = &self.conditions
+            && c.0.len() > u8::MAX as usize{
+            return Err(Error::new(ErrorKind::InvalidInput, "The condition count exceed the limit of 255"))
+        }
         let mut binary = vec![3u8];
         binary.push(self.container.len() as u8);
         binary.extend_from_slice(self.container.as_bytes());
@@ -496,12 +558,12 @@ pub struct EditRow{
     pub col_nam : Vec<String>,
     pub col_val : Vec<AlbaTypes>,
     pub container : String,
-    pub conditions : (Vec<(String,LogicalOperator,AlbaTypes)>,Vec<(u8,char)>)
+    pub conditions : Conditions
 }
 #[derive(Debug, Clone, PartialEq)]
 pub struct DeleteRow{
     pub container : String,
-    pub conditions : Option<(Vec<(String,LogicalOperator,AlbaTypes)>,Vec<(usize,char)>)>
+    pub conditions : Option<Conditions<usize>>
 }
 #[derive(Debug, Clone, PartialEq)]
 pub struct DeleteContainer{
@@ -518,7 +580,7 @@ impl Compile for AlbaContainer{
         }
         let mut a = vec![];
         a.push(self.len()as u8);
-        a.extend_from_slice(&self.as_bytes());
+        a.extend_from_slice(self.as_bytes());
         Ok(a)
             
     }
@@ -532,7 +594,7 @@ impl StandAloneDecompile for AlbaContainer {
         }
         let offset = 0usize;
         Ok(
-            (String::from_utf8_lossy(&input[offset+1 as usize .. offset + 1 + input[offset] as usize]).to_string()) as AlbaContainer
+            (String::from_utf8_lossy(&input[offset+1 .. offset + 1 + input[offset] as usize]).to_string()) as AlbaContainer
         )
     }
 }
@@ -555,7 +617,7 @@ impl <T:Compile+StandAloneDecompile> Compile for Vec<T>  {
 #[derive(Debug, Clone, PartialEq)]
 pub struct Search{
     pub container : AlbaContainer,
-    pub conditions : (Vec<(String,LogicalOperator,AlbaTypes)>,Vec<(u8,char)>),
+    pub conditions : Conditions,
     pub col_nam : Vec<String>,
 }
 
@@ -571,7 +633,7 @@ impl Compile for Search {
                 return Err(Error::new(ErrorKind::InvalidInput, "Column names lengths cannot be higher than 255"))
             }
             bytes.push(i.len() as u8);
-            bytes.extend_from_slice(&i.as_bytes());
+            bytes.extend_from_slice(i.as_bytes());
         }
         if self.conditions.0.len() > 255{
             return Err(Error::new(ErrorKind::InvalidInput, "Conditions count cannot be higher than 255"))
@@ -579,7 +641,7 @@ impl Compile for Search {
         bytes.push(self.conditions.0.len() as u8);
         for i in self.conditions.0.iter(){
             bytes.push(i.0.len() as u8);
-            bytes.extend_from_slice(&i.0.as_bytes());
+            bytes.extend_from_slice(i.0.as_bytes());
             bytes.push(i.1.id());
             bytes.extend_from_slice(&i.2.as_bytes());
         }
@@ -731,7 +793,7 @@ impl Compile for Commit{
             let bytes_string = co.as_bytes();
             let len = bytes_string.len() as u8;
             bytes.push(len);
-            bytes.extend_from_slice(&bytes_string);
+            bytes.extend_from_slice(bytes_string);
         }
         Ok(bytes)
     }
@@ -750,7 +812,7 @@ impl Compile for Rollback{
             }
             let bytes_string = co.as_bytes();
             bytes.push(bytes_string.len() as u8);
-            bytes.extend_from_slice(&bytes_string);
+            bytes.extend_from_slice(bytes_string);
         }
         Ok(bytes)
     }
@@ -848,9 +910,10 @@ impl Batch{
         let count = i32::from_le_bytes(count_bytes);
         
         let transaction = count < 0;
-        let command_count = count.abs() as usize;
+        let command_count = count.unsigned_abs() as usize;
         
-        let mut commands: Vec<Commands> = Vec::with_capacity(command_count);
+        // the count is untrusted, every command takes at least its 4 bytes length
+        let mut commands: Vec<Commands> = Vec::with_capacity(command_count.min((bytes.len() - 5) / 4));
         let mut offset = 5;        
         for _ in 0..command_count {
             if offset + 4 > bytes.len() {
@@ -884,3 +947,161 @@ impl Batch{
     }
 
 }
+
+// DISPLAY
+//
+// Every command renders as the statement of the text query language (see `parser::parse`) that produces it.
+
+fn write_names(f : &mut fmt::Formatter<'_>, names : &[String]) -> fmt::Result{
+    for (i,n) in names.iter().enumerate(){
+        if i > 0{
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", quote_name(n))?;
+    }
+    Ok(())
+}
+fn write_values(f : &mut fmt::Formatter<'_>, values : &[AlbaTypes]) -> fmt::Result{
+    write!(f, "(")?;
+    for (i,v) in values.iter().enumerate(){
+        if i > 0{
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", literal(v))?;
+    }
+    write!(f, ")")
+}
+/// Writes the ` WHERE ...` clause of a chain. The gate joining the condition `i` to the previous one is the
+/// logic entry with the index `i + shift`: searches index their gates from 0, edits and deletes from 1.
+fn write_conditions<I : Copy + Into<usize>>(f : &mut fmt::Formatter<'_>, conditions : &[Condition], logic : &[(I,char)], shift : usize) -> fmt::Result{
+    for (i,(column,operator,value)) in conditions.iter().enumerate(){
+        if i == 0{
+            write!(f, " WHERE ")?;
+        }else{
+            let or = logic.iter().any(|(index,gate)| (*index).into() == i + shift && (*gate == 'o' || *gate == 'O'));
+            write!(f, " {} ", if or {"OR"} else {"AND"})?;
+        }
+        match operator{
+            LogicalOperator::In | LogicalOperator::NotIn => write!(f, "{} {} ({})", quote_name(column), operator, literal(value))?,
+            _ => write!(f, "{} {} {}", quote_name(column), operator, literal(value))?,
+        }
+    }
+    Ok(())
+}
+
+impl fmt::Display for Commands{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self{
+            Commands::CreateContainer(struc) => struc.fmt(f),
+            Commands::CreateRow(struc) => struc.fmt(f),
+            Commands::EditRow(struc) => struc.fmt(f),
+            Commands::DeleteRow(struc) => struc.fmt(f),
+            Commands::DeleteContainer(struc) => struc.fmt(f),
+            Commands::Search(struc) => struc.fmt(f),
+            Commands::Commit(struc) => struc.fmt(f),
+            Commands::Rollback(struc) => struc.fmt(f),
+            Commands::BatchCreateRows(struc) => struc.fmt(f),
+            Commands::Batch(struc) => struc.fmt(f),
+        }
+    }
+}
+impl fmt::Display for CreateContainer{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        write!(f, "CREATE CONTAINER {} (", quote_name(&self.name))?;
+        for (i,(name,kind)) in self.col_nam.iter().zip(self.col_val.iter()).enumerate(){
+            if i > 0{
+                write!(f, ", ")?;
+            }
+            match column_type_name(*kind){
+                Some(kind) => write!(f, "{} {}", quote_name(name), kind)?,
+                None => write!(f, "{} {}", quote_name(name), kind)?,
+            }
+        }
+        write!(f, ")")
+    }
+}
+impl fmt::Display for CreateRow{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        write!(f, "INSERT INTO {} (", quote_name(&self.container))?;
+        write_names(f, &self.col_nam)?;
+        write!(f, ") VALUES ")?;
+        write_values(f, &self.col_val)
+    }
+}
+impl fmt::Display for BatchCreateRows{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        write!(f, "INSERT INTO {} (", quote_name(&self.container))?;
+        write_names(f, &self.col_nam)?;
+        write!(f, ") VALUES ")?;
+        for (i,row) in self.col_val.iter().enumerate(){
+            if i > 0{
+                write!(f, ", ")?;
+            }
+            write_values(f, row)?;
+        }
+        Ok(())
+    }
+}
+impl fmt::Display for EditRow{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        write!(f, "UPDATE {} SET ", quote_name(&self.container))?;
+        for (i,(name,value)) in self.col_nam.iter().zip(self.col_val.iter()).enumerate(){
+            if i > 0{
+                write!(f, ", ")?;
+            }
+            write!(f, "{} = {}", quote_name(name), literal(value))?;
+        }
+        write_conditions(f, &self.conditions.0, &self.conditions.1, 1)
+    }
+}
+impl fmt::Display for DeleteRow{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        write!(f, "DELETE FROM {}", quote_name(&self.container))?;
+        match &self.conditions{
+            Some((conditions,logic)) => write_conditions(f, conditions, logic, 1),
+            None => Ok(())
+        }
+    }
+}
+impl fmt::Display for DeleteContainer{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        write!(f, "DROP CONTAINER {}", quote_name(&self.container))
+    }
+}
+impl fmt::Display for Search{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        write!(f, "SELECT ")?;
+        if self.col_nam.is_empty(){
+            write!(f, "*")?;
+        }else{
+            write_names(f, &self.col_nam)?;
+        }
+        write!(f, " FROM {}", quote_name(&self.container))?;
+        write_conditions(f, &self.conditions.0, &self.conditions.1, 0)
+    }
+}
+impl fmt::Display for Commit{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match &self.container{
+            Some(c) => write!(f, "COMMIT {}", quote_name(c)),
+            None => write!(f, "COMMIT")
+        }
+    }
+}
+impl fmt::Display for Rollback{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match &self.container{
+            Some(c) => write!(f, "ROLLBACK {}", quote_name(c)),
+            None => write!(f, "ROLLBACK")
+        }
+    }
+}
+impl fmt::Display for Batch{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        write!(f, "{}", if self.transaction {"BEGIN TRANSACTION;"} else {"BEGIN;"})?;
+        for c in self.commands.iter(){
+            write!(f, " {};", c)?;
+        }
+        write!(f, " END")
+    }
+}
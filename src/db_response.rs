@@ -1,4 +1,6 @@
-use crate::{albastream::Error, dynamic_int::DynamicInteger, types::AlbaTypes};
+use std::{fmt, io::Write};
+
+use crate::{albastream::Error, dynamic_int::DynamicInteger, export, types::AlbaTypes};
 
 #[derive(Debug)]
 pub struct Row (pub Vec<AlbaTypes>);
@@ -30,6 +32,29 @@ impl Row{
         Ok((Row(row),bytes_readen))
     }
 }
+/// Decodes the rows of a response payload one at a time, without materializing the whole `row_list`.
+pub struct RowIter<'a>{
+    input : &'a [u8],
+    offset : usize
+}
+impl Iterator for RowIter<'_>{
+    type Item = Result<Row,Error>;
+    fn next(&mut self) -> Option<Self::Item>{
+        if self.offset >= self.input.len(){
+            return None
+        }
+        match Row::decode(&self.input[self.offset..]){
+            Ok((row,read)) => {
+                self.offset += read;
+                Some(Ok(row))
+            },
+            Err(e) => {
+                self.offset = self.input.len();
+                Some(Err(e))
+            }
+        }
+    }
+}
 pub struct DBResponse{
     length : u64,
     pub row_list : Vec<Row>
@@ -60,7 +85,73 @@ impl DBResponse {
         r.shrink_to_fit();
         DBResponse { length: r.len() as u64, row_list: r }
     }
+    /// Iterates over the rows of an encoded response, decoding them lazily.
+    pub fn iter_rows(i : &[u8]) -> RowIter<'_>{
+        RowIter{input:i,offset:0}
+    }
     pub fn from_bytes(i : &[u8]) -> Result<DBResponse,Error>{
         Ok(DBResponse::decode(i)?.0)
     }
+
+    /// Writes the rows as CSV with a `columns` header, see `export::write_csv`.
+    pub fn write_csv<W : Write>(&self, writer : W, columns : &[String]) -> Result<usize,Error>{
+        export::write_csv(writer, columns, self.row_list.iter().map(Ok::<&Row,Error>))
+    }
+    /// Writes the rows as JSON Lines keyed by `columns`, see `export::write_jsonl`.
+    pub fn write_jsonl<W : Write>(&self, writer : W, columns : &[String]) -> Result<usize,Error>{
+        export::write_jsonl(writer, columns, self.row_list.iter().map(Ok::<&Row,Error>))
+    }
+    /// Converts the rows into an Arrow `RecordBatch`, see `export::record_batch`.
+    #[cfg(feature = "arrow")]
+    pub fn to_record_batch(&self, columns : &[String], wide : export::WideIntegers) -> Result<arrow_array::RecordBatch,Error>{
+        export::record_batch(columns, self.row_list.iter().map(Ok::<&Row,Error>), wide)
+    }
+
+    /// Renders the rows as an aligned ASCII table with a `columns` header row, followed by the row count.
+    ///
+    /// Control characters in the values are escaped so every row stays on one line.
+    pub fn to_table(&self, columns : &[String]) -> String{
+        let cell = |v : &AlbaTypes| -> String{
+            v.to_string().chars().map(|c| if c.is_control() {c.escape_default().to_string()} else {c.to_string()}).collect()
+        };
+        let cells : Vec<Vec<String>> = self.row_list.iter().map(|r| r.0.iter().map(cell).collect()).collect();
+        let width_count = cells.iter().map(|r| r.len()).chain(std::iter::once(columns.len())).max().unwrap_or(0);
+        let mut widths = vec![0usize; width_count];
+        for row in cells.iter().map(|r| r.as_slice()).chain(std::iter::once(columns)){
+            for (i,c) in row.iter().enumerate(){
+                widths[i] = widths[i].max(c.chars().count());
+            }
+        }
+        let mut table = String::new();
+        let separator : String = widths.iter().map(|w| format!("+{}", "-".repeat(w + 2))).collect::<String>() + "+\n";
+        let line = |table : &mut String, row : &[String]|{
+            for (i,w) in widths.iter().enumerate(){
+                let c = row.get(i).map(|c| c.as_str()).unwrap_or("");
+                table.push_str(&format!("| {}{} ", c, " ".repeat(w - c.chars().count())));
+            }
+            table.push_str("|\n");
+        };
+        if width_count > 0{
+            table.push_str(&separator);
+            if !columns.is_empty(){
+                line(&mut table, columns);
+                table.push_str(&separator);
+            }
+            for row in cells.iter(){
+                line(&mut table, row);
+            }
+            if !cells.is_empty(){
+                table.push_str(&separator);
+            }
+        }
+        table.push_str(&format!("({} row{})", self.row_list.len(), if self.row_list.len() == 1 {""} else {"s"}));
+        table
+    }
+}
+
+/// An aligned ASCII table of the rows, without header. See `DBResponse::to_table` to name the columns.
+impl fmt::Display for DBResponse{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        write!(f, "{}", self.to_table(&[]))
+    }
 }
\ No newline at end of file
@@ -0,0 +1,309 @@
+use std::{fs::{self, File}, io::{BufReader, BufWriter, Read, Write}, path::{Path, PathBuf}};
+
+use crate::{albastream::{CompiledAlba, Error, ErrorKind}, bulk::BulkInsert, commands::{Commands, Search}, db_response::{DBResponse, Row}, handler::CreateContainerBuilder, types::AlbaTypes};
+
+// A dump file is, in little endian:
+//
+//   b"TYTODUMP", u16 version
+//   u8 container name length, container name
+//   u8 column count, then per column: u8 name length, name, u8 column type
+//   u64 row count
+//   per row: u32 length, the row encoded as in a response (`Row::encode`)
+
+const MAGIC : &[u8; 8] = b"TYTODUMP";
+/// The version of the dump files written by `Client::dump`.
+pub const DUMP_VERSION : u16 = 1;
+const PROGRESS_ROWS : u64 = 10_000;
+
+fn io_error(e : std::io::Error) -> Error{
+    Error::new(ErrorKind::Other, &e.to_string())
+}
+
+fn invalid(message : &str) -> Error{
+    Error::new(ErrorKind::InvalidInput, message)
+}
+
+/// The head of a dump file: the container it was taken from, its schema and its row count.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DumpHeader{
+    pub version : u16,
+    pub container : String,
+    /// The columns with their column type (e.g. `BIGINT`), in dump order.
+    pub columns : Vec<(String,u8)>,
+    pub rows : u64,
+}
+
+impl DumpHeader{
+    fn write<W : Write>(&self, writer : &mut W) -> Result<(),Error>{
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&self.version.to_le_bytes());
+        short_text(&mut bytes, &self.container)?;
+        if self.columns.len() > u8::MAX as usize{
+            return Err(invalid("The column count exceed the limit"))
+        }
+        bytes.push(self.columns.len() as u8);
+        for (name, column_type) in self.columns.iter(){
+            short_text(&mut bytes, name)?;
+            bytes.push(*column_type);
+        }
+        bytes.extend_from_slice(&self.rows.to_le_bytes());
+        writer.write_all(&bytes).map_err(io_error)
+    }
+    fn read<R : Read>(reader : &mut R) -> Result<DumpHeader,Error>{
+        let mut magic = [0u8; 8];
+        read_exact(reader, &mut magic)?;
+        if &magic != MAGIC{
+            return Err(invalid("Not a TytoDB dump file"))
+        }
+        let mut version = [0u8; 2];
+        read_exact(reader, &mut version)?;
+        let version = u16::from_le_bytes(version);
+        if version == 0 || version > DUMP_VERSION{
+            return Err(invalid(&format!("Unsupported dump version {}, the latest known is {}", version, DUMP_VERSION)))
+        }
+        let container = read_short_text(reader)?;
+        let mut count = [0u8; 1];
+        read_exact(reader, &mut count)?;
+        let mut columns = Vec::with_capacity(count[0] as usize);
+        for _ in 0..count[0]{
+            let name = read_short_text(reader)?;
+            let mut column_type = [0u8; 1];
+            read_exact(reader, &mut column_type)?;
+            columns.push((name, column_type[0]));
+        }
+        let mut rows = [0u8; 8];
+        read_exact(reader, &mut rows)?;
+        Ok(DumpHeader{version,container,columns,rows:u64::from_le_bytes(rows)})
+    }
+    /// A `CreateContainerBuilder` recreating the dumped container as `container`.
+    pub fn create_container(&self, container : &str) -> CreateContainerBuilder{
+        self.columns.iter().fold(CreateContainerBuilder::new().put_container(container.to_string()), |builder, (name, column_type)| builder.insert_header(name.clone(), *column_type))
+    }
+}
+
+fn short_text(bytes : &mut Vec<u8>, text : &str) -> Result<(),Error>{
+    if text.len() > u8::MAX as usize{
+        return Err(invalid(&format!("`{}` is longer than 255 bytes", text)))
+    }
+    bytes.push(text.len() as u8);
+    bytes.extend_from_slice(text.as_bytes());
+    Ok(())
+}
+
+fn read_exact<R : Read>(reader : &mut R, buffer : &mut [u8]) -> Result<(),Error>{
+    reader.read_exact(buffer).map_err(|e| match e.kind(){
+        std::io::ErrorKind::UnexpectedEof => Error::new(ErrorKind::UnexpectedEof, "The dump file is truncated"),
+        _ => io_error(e),
+    })
+}
+
+fn read_short_text<R : Read>(reader : &mut R) -> Result<String,Error>{
+    let mut len = [0u8; 1];
+    read_exact(reader, &mut len)?;
+    let mut text = vec![0u8; len[0] as usize];
+    read_exact(reader, &mut text)?;
+    String::from_utf8(text).map_err(|_| invalid("A name of the dump file is not UTF-8"))
+}
+
+/// How far a dump or a restore went: `rows` out of `total`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress{
+    pub rows : u64,
+    pub total : u64,
+}
+
+/// The `Search` of every row of `container`, projected on `columns`, whose reply `write_dump` writes.
+pub fn dump_search(container : &str, columns : &[(&str,u8)]) -> Result<CompiledAlba,Error>{
+    Commands::Search(Search{
+        container: container.to_string(),
+        conditions: (Vec::new(), Vec::new()),
+        col_nam: columns.iter().map(|(c, _)| c.to_string()).collect(),
+    }).compile()
+}
+
+fn partial_path(path : &Path) -> PathBuf{
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    PathBuf::from(partial)
+}
+
+/// Write the dump of the encoded rows of `payload` (the reply of `dump_search`) to `path`, see `Client::dump`.
+/// Returns the row count.
+///
+/// The dump is written to `<path>.partial` and renamed once complete, so a failed dump never leaves a truncated
+/// file behind and can just be started again.
+pub fn write_dump<P : FnMut(Progress)>(path : &Path, container : &str, columns : &[(&str,u8)], payload : &[u8], mut progress : P) -> Result<u64,Error>{
+    let mut total = 0;
+    for row in DBResponse::iter_rows(payload){
+        if row?.0.len() != columns.len(){
+            return Err(invalid("A row of the container doesn't have a value per dumped column"))
+        }
+        total += 1;
+    }
+    let header = DumpHeader{
+        version: DUMP_VERSION,
+        container: container.to_string(),
+        columns: columns.iter().map(|(c, t)| (c.to_string(), *t)).collect(),
+        rows: total,
+    };
+    let partial = partial_path(path);
+    let written = (|| {
+        let mut writer = BufWriter::new(File::create(&partial).map_err(io_error)?);
+        header.write(&mut writer)?;
+        let mut rows = 0;
+        for row in DBResponse::iter_rows(payload){
+            let encoded = row?.encode();
+            writer.write_all(&(encoded.len() as u32).to_le_bytes()).map_err(io_error)?;
+            writer.write_all(&encoded).map_err(io_error)?;
+            rows += 1;
+            if rows % PROGRESS_ROWS == 0{
+                progress(Progress{rows,total});
+            }
+        }
+        writer.into_inner().map_err(|e| io_error(e.into_error()))?.sync_all().map_err(io_error)?;
+        Ok(rows)
+    })();
+    let rows = match written{
+        Ok(rows) => rows,
+        Err(e) => {
+            let _ = fs::remove_file(&partial);
+            return Err(e)
+        }
+    };
+    fs::rename(&partial, path).map_err(io_error)?;
+    progress(Progress{rows,total});
+    Ok(rows)
+}
+
+/// Reads a dump file, iterating over its rows.
+///
+/// The iteration ends at the last row of the dump or at the first row that can't be read, see `error`.
+pub struct DumpReader<R : Read>{
+    header : DumpHeader,
+    reader : R,
+    read : u64,
+    error : Option<Error>,
+}
+
+impl DumpReader<BufReader<File>>{
+    pub fn open<P : AsRef<Path>>(path : P) -> Result<Self,Error>{
+        DumpReader::new(BufReader::new(File::open(path).map_err(io_error)?))
+    }
+}
+
+impl<R : Read> DumpReader<R>{
+    /// Read the header of the dump, failing if it isn't a dump of a supported version.
+    pub fn new(mut reader : R) -> Result<Self,Error>{
+        let header = DumpHeader::read(&mut reader)?;
+        Ok(DumpReader{header,reader,read:0,error:None})
+    }
+    pub fn header(&self) -> &DumpHeader{
+        &self.header
+    }
+    /// The count of rows read so far.
+    pub fn rows_read(&self) -> u64{
+        self.read
+    }
+    /// The error that ended the iteration before the last row.
+    pub fn error(&self) -> Option<&Error>{
+        self.error.as_ref()
+    }
+    /// Skip the next `rows` rows.
+    pub fn skip_rows(&mut self, rows : u64) -> Result<(),Error>{
+        for _ in 0..rows{
+            if self.next().is_none(){
+                return Err(self.error.take().unwrap_or_else(|| Error::new(ErrorKind::InvalidInput, "The dump has fewer rows than the rows to skip")))
+            }
+        }
+        Ok(())
+    }
+    fn read_row(&mut self) -> Result<Row,Error>{
+        let mut len = [0u8; 4];
+        read_exact(&mut self.reader, &mut len)?;
+        let mut encoded = vec![0u8; u32::from_le_bytes(len) as usize];
+        read_exact(&mut self.reader, &mut encoded)?;
+        let (row, read) = Row::decode(&encoded)?;
+        if read != encoded.len() || row.0.len() != self.header.columns.len(){
+            return Err(invalid(&format!("Row {} of the dump is malformed", self.read)))
+        }
+        Ok(row)
+    }
+}
+
+impl<R : Read> Iterator for DumpReader<R>{
+    type Item = Vec<AlbaTypes>;
+    fn next(&mut self) -> Option<Vec<AlbaTypes>>{
+        if self.read >= self.header.rows || self.error.is_some(){
+            return None
+        }
+        match self.read_row(){
+            Ok(row) => {
+                self.read += 1;
+                Some(row.0)
+            },
+            Err(e) => {
+                self.error = Some(e);
+                None
+            }
+        }
+    }
+}
+
+/// The options of `Client::restore`.
+///
+/// The container is created, then the rows are reloaded in order with one `BatchCreateRows` per chunk. When a
+/// chunk fails the restore stops and its error tells how many rows were restored; restoring again with
+/// `resume_at` set to that count skips the creation of the container and the restored rows, `resume_at(0)` when
+/// the container was created but no chunk was restored.
+#[derive(Debug, Clone)]
+pub struct Restore{
+    container : Option<String>,
+    resume_at : Option<u64>,
+    bulk : BulkInsert,
+}
+
+impl Default for Restore{
+    fn default() -> Self{
+        Restore{container:None,resume_at:None,bulk:BulkInsert::new("", Vec::new())}
+    }
+}
+
+impl Restore{
+    pub fn new() -> Self{
+        Self::default()
+    }
+    /// Restore into `container` instead of the dumped container.
+    pub fn into_container(mut self, container : &str) -> Self{
+        self.container = Some(container.to_string());
+        self
+    }
+    /// Resume a restore that failed after creating the container and restoring `rows` rows.
+    pub fn resume_at(mut self, rows : u64) -> Self{
+        self.resume_at = Some(rows);
+        self
+    }
+    /// The size of the chunks, the container and columns of `bulk` are ignored. Chunks are sent one by one.
+    pub fn bulk(mut self, bulk : BulkInsert) -> Self{
+        self.bulk = bulk;
+        self
+    }
+    /// Skip the restored rows of `reader`, returning the container to create (unless resuming), the chunking of
+    /// the remaining rows and the count of restored rows.
+    pub fn prepare<R : Read>(&self, reader : &mut DumpReader<R>) -> Result<(Option<CreateContainerBuilder>, BulkInsert, u64),Error>{
+        let header = reader.header();
+        let container = self.container.clone().unwrap_or_else(|| header.container.clone());
+        let create = self.resume_at.is_none().then(|| header.create_container(&container));
+        let mut bulk = self.bulk.clone().concurrency(1).transaction(false);
+        bulk.container = container;
+        bulk.columns = header.columns.iter().map(|(c, _)| c.clone()).collect();
+        let restored = self.resume_at.unwrap_or(0);
+        reader.skip_rows(restored)?;
+        Ok((create, bulk, restored))
+    }
+}
+
+/// The error of a restore that stopped after creating the container and restoring `restored` rows, telling where
+/// to resume.
+pub fn restore_failed(error : &Error, restored : u64) -> Error{
+    Error::new(error.kind(), &format!("{} (restore stopped after {} rows, resume at {})", error.message(), restored, restored))
+}
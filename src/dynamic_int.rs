@@ -2,8 +2,8 @@ use crate::albastream::{Error, ErrorKind};
 
 pub fn vec_from_two_vec<T: Clone>(vec0: &[T], vec1: &[T]) -> Vec<T> {
     let mut vec2 = Vec::with_capacity(vec0.len() + vec1.len());
-    vec2.extend_from_slice(&vec0);
-    vec2.extend_from_slice(&vec1);
+    vec2.extend_from_slice(vec0);
+    vec2.extend_from_slice(vec1);
     vec2
 }
 pub enum DynamicInteger {
@@ -0,0 +1,144 @@
+#[cfg(any(feature="thread", feature="tokio", feature="asyncstd"))]
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+#[cfg(any(feature="thread", feature="tokio", feature="asyncstd"))]
+use crate::albastream::{Error, ErrorKind};
+
+/// The hosts of a client, see `Client::connect_endpoints`.
+///
+/// Requests go to the primary. When its connection fails the client reconnects, to the primary again and then to
+/// the standbys in order, the hosts that failed their last health check being tried last. The client stays on the
+/// host it failed over to. With replicas, searches go to a replica (failing over between them) and fall back to
+/// the primary when no replica can be reached; everything else, and every request of a transaction, goes to the
+/// primary. The heartbeat pings the connections and checks the other hosts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoints{
+    primary : Vec<String>,
+    replicas : Vec<String>,
+}
+
+impl Endpoints{
+    pub fn new(primary : &str) -> Self{
+        Endpoints{primary:vec![primary.to_string()],replicas:Vec::new()}
+    }
+    /// Add a standby, taking over when the primary and the standbys added before can't be reached.
+    pub fn standby(mut self, host : &str) -> Self{
+        self.primary.push(host.to_string());
+        self
+    }
+    /// Add a replica serving searches.
+    pub fn replica(mut self, host : &str) -> Self{
+        self.replicas.push(host.to_string());
+        self
+    }
+    /// The primary, then the standbys.
+    pub fn primary(&self) -> &[String]{
+        &self.primary
+    }
+    pub fn replicas(&self) -> &[String]{
+        &self.replicas
+    }
+}
+
+impl From<&str> for Endpoints{
+    fn from(host : &str) -> Self{
+        Endpoints::new(host)
+    }
+}
+
+/// A group of hosts a connection fails over between, with the host it is connected to.
+#[cfg(any(feature="thread", feature="tokio", feature="asyncstd"))]
+pub(crate) struct Hosts{
+    hosts : Vec<String>,
+    current : AtomicUsize,
+    healthy : Vec<AtomicBool>,
+    generation : AtomicU64,
+}
+
+#[cfg(any(feature="thread", feature="tokio", feature="asyncstd"))]
+impl Hosts{
+    pub(crate) fn new(hosts : &[String]) -> Self{
+        Hosts{hosts:hosts.to_vec(),current:AtomicUsize::new(0),healthy:hosts.iter().map(|_| AtomicBool::new(true)).collect(),generation:AtomicU64::new(0)}
+    }
+    pub(crate) fn host(&self, index : usize) -> &str{
+        &self.hosts[index]
+    }
+    pub(crate) fn current(&self) -> usize{
+        self.current.load(Ordering::Relaxed)
+    }
+    /// The hosts to connect to in order: the current one, the healthy ones after it, then the others.
+    pub(crate) fn candidates(&self) -> Vec<usize>{
+        let current = self.current();
+        let (mut candidates, unhealthy) : (Vec<usize>, Vec<usize>) = (0..self.hosts.len())
+            .map(|i| (current + i) % self.hosts.len())
+            .partition(|&i| i == current || self.healthy[i].load(Ordering::Relaxed));
+        candidates.extend(unhealthy);
+        candidates
+    }
+    /// The hosts other than the current one, to be health checked.
+    pub(crate) fn others(&self) -> impl Iterator<Item = usize> + '_{
+        let current = self.current();
+        (0..self.hosts.len()).filter(move |&i| i != current)
+    }
+    /// A copy for a connection of its own, failing over without moving the current host of `self`.
+    pub(crate) fn cursor(&self) -> Hosts{
+        Hosts{
+            hosts:self.hosts.clone(),
+            current:AtomicUsize::new(self.current()),
+            healthy:self.healthy.iter().map(|h| AtomicBool::new(h.load(Ordering::Relaxed))).collect(),
+            generation:AtomicU64::new(0)
+        }
+    }
+    pub(crate) fn connected(&self, index : usize){
+        self.current.store(index, Ordering::Relaxed);
+        self.healthy[index].store(true, Ordering::Relaxed);
+    }
+    pub(crate) fn checked(&self, index : usize, healthy : bool){
+        self.healthy[index].store(healthy, Ordering::Relaxed);
+    }
+    /// How many times the connection of these hosts was replaced, see `replaced`.
+    pub(crate) fn generation(&self) -> u64{
+        self.generation.load(Ordering::Acquire)
+    }
+    /// The connection was replaced by a new one, ending the session (and the transaction) the old one held.
+    ///
+    /// Called with the connection still locked, so no request goes through the new connection before the
+    /// generation moves on.
+    pub(crate) fn replaced(&self){
+        self.generation.fetch_add(1, Ordering::AcqRel);
+    }
+    /// Fails when the connection was replaced since `generation`, the session begun then being lost.
+    pub(crate) fn check_session(&self, generation : u64) -> Result<(),Error>{
+        if self.generation() != generation{
+            return Err(Error::new(ErrorKind::Connection, "The connection was re-established since the transaction began, the transaction is lost"))
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, any(feature="thread", feature="tokio", feature="asyncstd")))]
+mod tests{
+    use super::*;
+
+    #[test]
+    fn replacing_the_connection_ends_the_session(){
+        let hosts = Hosts::new(&["a:4287".to_string(), "b:4287".to_string()]);
+        let began = hosts.generation();
+        assert!(hosts.check_session(began).is_ok());
+        hosts.connected(1);
+        assert!(hosts.check_session(began).is_ok());
+        hosts.replaced();
+        let e = hosts.check_session(began).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::Connection);
+        assert!(hosts.check_session(hosts.generation()).is_ok());
+    }
+
+    #[test]
+    fn cursors_have_sessions_of_their_own(){
+        let hosts = Hosts::new(&["a:4287".to_string()]);
+        let began = hosts.generation();
+        let cursor = hosts.cursor();
+        cursor.replaced();
+        assert!(hosts.check_session(began).is_ok());
+        assert_eq!(cursor.current(), hosts.current());
+    }
+}
@@ -0,0 +1,361 @@
+use std::{borrow::Borrow, io::Write};
+
+use crate::{albastream::{Error, ErrorKind}, db_response::Row, types::AlbaTypes};
+
+// The exports of the rows of a search, `columns` being its projection (`SearchBuilder::column_names`).
+//
+// The rows come from `DBResponse::row_list` or, without materializing the response, from `DBResponse::iter_rows`.
+// Values are written the way `import::Importer` reads them: bytes in standard base64, geo as `lat,lon` in CSV and
+// `[lat, lon]` in JSON.
+
+fn io_error(e : std::io::Error) -> Error{
+    Error::new(ErrorKind::Other, &e.to_string())
+}
+
+/// Write the rows as CSV, with a `columns` header unless it is empty. Returns the row count.
+pub fn write_csv<W : Write, R : Borrow<Row>, I : IntoIterator<Item = Result<R,Error>>>(mut writer : W, columns : &[String], rows : I) -> Result<usize,Error>{
+    if !columns.is_empty(){
+        let header = columns.iter().map(|c| csv_field(c)).collect::<Vec<String>>().join(",");
+        writeln!(writer, "{}", header).map_err(io_error)?;
+    }
+    let mut count = 0;
+    for row in rows{
+        let row = row?;
+        let line = row.borrow().0.iter().map(|v| csv_field(&text(v))).collect::<Vec<String>>().join(",");
+        writeln!(writer, "{}", line).map_err(io_error)?;
+        count += 1;
+    }
+    writer.flush().map_err(io_error)?;
+    Ok(count)
+}
+
+/// Write the rows as JSON Lines: one object keyed by `columns` per row, or an array when the row doesn't have a
+/// value per column (e.g. a search of every column). Returns the row count.
+pub fn write_jsonl<W : Write, R : Borrow<Row>, I : IntoIterator<Item = Result<R,Error>>>(mut writer : W, columns : &[String], rows : I) -> Result<usize,Error>{
+    let mut count = 0;
+    let mut line = String::new();
+    for row in rows{
+        let row = row?;
+        let values = &row.borrow().0;
+        line.clear();
+        if values.len() == columns.len() && !columns.is_empty(){
+            line.push('{');
+            for (i, (column, value)) in columns.iter().zip(values).enumerate(){
+                if i > 0{
+                    line.push(',');
+                }
+                json_string(column, &mut line);
+                line.push(':');
+                json_value(value, &mut line);
+            }
+            line.push('}');
+        }else{
+            line.push('[');
+            for (i, value) in values.iter().enumerate(){
+                if i > 0{
+                    line.push(',');
+                }
+                json_value(value, &mut line);
+            }
+            line.push(']');
+        }
+        writeln!(writer, "{}", line).map_err(io_error)?;
+        count += 1;
+    }
+    writer.flush().map_err(io_error)?;
+    Ok(count)
+}
+
+fn text(value : &AlbaTypes) -> String{
+    match value{
+        AlbaTypes::Bytes(b) => base64(b),
+        AlbaTypes::Geo((lat, lon)) => format!("{},{}", lat, lon),
+        v => v.to_string(),
+    }
+}
+
+fn csv_field(field : &str) -> String{
+    if field.contains([',', '"', '\n', '\r']){
+        format!("\"{}\"", field.replace('"', "\"\""))
+    }else{
+        field.to_string()
+    }
+}
+
+fn json_string(s : &str, out : &mut String){
+    out.push('"');
+    for c in s.chars(){
+        match c{
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn json_float(v : f64, out : &mut String){
+    // JSON has no NaN nor infinities, they are written as the strings the importer parses back
+    if v.is_finite(){
+        out.push_str(&v.to_string())
+    }else{
+        json_string(&v.to_string(), out)
+    }
+}
+
+fn json_value(value : &AlbaTypes, out : &mut String){
+    match value{
+        AlbaTypes::String(s) => json_string(s, out),
+        // beyond 64 bits JSON numbers lose precision in most readers
+        AlbaTypes::U128(v) if u64::try_from(*v).is_err() => json_string(&v.to_string(), out),
+        AlbaTypes::I128(v) if i64::try_from(*v).is_err() => json_string(&v.to_string(), out),
+        AlbaTypes::F32(v) => json_float(*v as f64, out),
+        AlbaTypes::F64(v) => json_float(*v, out),
+        AlbaTypes::Bytes(b) => json_string(&base64(b), out),
+        AlbaTypes::Geo((lat, lon)) => {
+            out.push('[');
+            json_float(*lat, out);
+            out.push(',');
+            json_float(*lon, out);
+            out.push(']');
+        },
+        v => out.push_str(&v.to_string()),
+    }
+}
+
+fn base64(bytes : &[u8]) -> String{
+    const ALPHABET : &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3){
+        let n = (chunk[0] as u32) << 16 | (*chunk.get(1).unwrap_or(&0) as u32) << 8 | *chunk.get(2).unwrap_or(&0) as u32;
+        for i in 0..4{
+            if i <= chunk.len(){
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            }else{
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(feature = "arrow")]
+pub use self::arrow::*;
+
+#[cfg(feature = "arrow")]
+mod arrow{
+    use std::{borrow::Borrow, sync::Arc};
+
+    use arrow_array::{
+        ArrayRef, RecordBatch, StructArray,
+        builder::{BinaryBuilder, BooleanBuilder, Decimal256Builder, FixedSizeBinaryBuilder, Float32Builder, Float64Builder, Int32Builder, Int64Builder, StringBuilder, UInt8Builder, UInt16Builder, UInt32Builder, UInt64Builder},
+        NullArray,
+    };
+    use arrow_buffer::i256;
+    use arrow_schema::{DataType, Field, Fields, Schema, SchemaRef};
+
+    use crate::{albastream::{Error, ErrorKind}, db_response::Row, types::AlbaTypes};
+
+    /// How the `U128` and `I128` columns are represented in Arrow.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub enum WideIntegers{
+        /// `Decimal256(39, 0)`, exact for both.
+        #[default]
+        Decimal,
+        /// `FixedSizeBinary(16)`, the little endian bytes of the value.
+        FixedSizeBinary,
+    }
+
+    fn arrow_error(e : impl std::fmt::Display) -> Error{
+        Error::new(ErrorKind::Other, &e.to_string())
+    }
+
+    fn geo_fields() -> Fields{
+        Fields::from(vec![Field::new("lat", DataType::Float64, false), Field::new("lon", DataType::Float64, false)])
+    }
+
+    /// The Arrow type of the values like `value`.
+    pub fn data_type(value : &AlbaTypes, wide : WideIntegers) -> DataType{
+        match value{
+            AlbaTypes::String(_) => DataType::Utf8,
+            AlbaTypes::U8(_) => DataType::UInt8,
+            AlbaTypes::U16(_) => DataType::UInt16,
+            AlbaTypes::U32(_) => DataType::UInt32,
+            AlbaTypes::U64(_) => DataType::UInt64,
+            AlbaTypes::I32(_) => DataType::Int32,
+            AlbaTypes::I64(_) => DataType::Int64,
+            AlbaTypes::U128(_) | AlbaTypes::I128(_) => match wide{
+                WideIntegers::Decimal => DataType::Decimal256(39, 0),
+                WideIntegers::FixedSizeBinary => DataType::FixedSizeBinary(16),
+            },
+            AlbaTypes::F32(_) => DataType::Float32,
+            AlbaTypes::F64(_) => DataType::Float64,
+            AlbaTypes::Bool(_) => DataType::Boolean,
+            AlbaTypes::Bytes(_) => DataType::Binary,
+            AlbaTypes::Geo(_) => DataType::Struct(geo_fields()),
+        }
+    }
+
+    /// The schema of rows like `first_row`, every column being `Null` without one. Columns are named after
+    /// `columns`, or `column_0`, `column_1`... when it is empty.
+    pub fn arrow_schema(columns : &[String], first_row : Option<&Row>, wide : WideIntegers) -> Result<Schema,Error>{
+        let width = first_row.map_or(columns.len(), |r| r.0.len());
+        if !columns.is_empty() && columns.len() != width{
+            return Err(Error::new(ErrorKind::InvalidInput, &format!("The rows have {} values for {} columns", width, columns.len())))
+        }
+        let fields = (0..width).map(|i| {
+            let name = columns.get(i).cloned().unwrap_or_else(|| format!("column_{}", i));
+            let data_type = first_row.map_or(DataType::Null, |r| data_type(&r.0[i], wide));
+            Field::new(name, data_type.clone(), data_type == DataType::Null)
+        });
+        Ok(Schema::new(fields.collect::<Vec<Field>>()))
+    }
+
+    /// Convert all the rows into one `RecordBatch`, the column types being those of the first row.
+    pub fn record_batch<R : Borrow<Row>, I : IntoIterator<Item = Result<R,Error>>>(columns : &[String], rows : I, wide : WideIntegers) -> Result<RecordBatch,Error>{
+        let rows = rows.into_iter().collect::<Result<Vec<R>,Error>>()?;
+        let schema = Arc::new(arrow_schema(columns, rows.first().map(|r| r.borrow()), wide)?);
+        batch(&schema, &rows)
+    }
+
+    /// Convert `rows` into a `RecordBatch` of `schema`.
+    pub(crate) fn batch<R : Borrow<Row>>(schema : &SchemaRef, rows : &[R]) -> Result<RecordBatch,Error>{
+        let mut arrays = Vec::with_capacity(schema.fields().len());
+        for (index, field) in schema.fields().iter().enumerate(){
+            arrays.push(column(field, index, rows)?);
+        }
+        if arrays.is_empty(){
+            return RecordBatch::try_new_with_options(schema.clone(), arrays, &arrow_array::RecordBatchOptions::new().with_row_count(Some(rows.len()))).map_err(arrow_error)
+        }
+        RecordBatch::try_new(schema.clone(), arrays).map_err(arrow_error)
+    }
+
+    fn column<R : Borrow<Row>>(field : &Field, index : usize, rows : &[R]) -> Result<ArrayRef,Error>{
+        let values = rows.iter().map(|r| {
+            r.borrow().0.get(index).ok_or_else(|| Error::new(ErrorKind::InvalidInput, &format!("A row has no value for column `{}`", field.name())))
+        });
+        let mismatch = |value : &AlbaTypes| Error::new(ErrorKind::InvalidInput, &format!("Column `{}` is {}, found the value `{}`", field.name(), field.data_type(), value));
+        macro_rules! primitive{
+            ($builder:ty, $variant:ident) => {{
+                let mut builder = <$builder>::with_capacity(rows.len());
+                for value in values{
+                    match value?{
+                        AlbaTypes::$variant(v) => builder.append_value(*v),
+                        other => return Err(mismatch(other))
+                    }
+                }
+                Arc::new(builder.finish()) as ArrayRef
+            }};
+        }
+        Ok(match field.data_type(){
+            DataType::Null => {
+                if let Some(value) = values.into_iter().next(){
+                    return Err(mismatch(value?))
+                }
+                Arc::new(NullArray::new(rows.len()))
+            },
+            DataType::Utf8 => {
+                let mut builder = StringBuilder::new();
+                for value in values{
+                    match value?{
+                        AlbaTypes::String(v) => builder.append_value(v),
+                        other => return Err(mismatch(other))
+                    }
+                }
+                Arc::new(builder.finish())
+            },
+            DataType::Binary => {
+                let mut builder = BinaryBuilder::new();
+                for value in values{
+                    match value?{
+                        AlbaTypes::Bytes(v) => builder.append_value(v),
+                        other => return Err(mismatch(other))
+                    }
+                }
+                Arc::new(builder.finish())
+            },
+            DataType::UInt8 => primitive!(UInt8Builder, U8),
+            DataType::UInt16 => primitive!(UInt16Builder, U16),
+            DataType::UInt32 => primitive!(UInt32Builder, U32),
+            DataType::UInt64 => primitive!(UInt64Builder, U64),
+            DataType::Int32 => primitive!(Int32Builder, I32),
+            DataType::Int64 => primitive!(Int64Builder, I64),
+            DataType::Float32 => primitive!(Float32Builder, F32),
+            DataType::Float64 => primitive!(Float64Builder, F64),
+            DataType::Boolean => primitive!(BooleanBuilder, Bool),
+            DataType::Decimal256(precision, scale) => {
+                let mut builder = Decimal256Builder::with_capacity(rows.len()).with_precision_and_scale(*precision, *scale).map_err(arrow_error)?;
+                for value in values{
+                    match value?{
+                        AlbaTypes::I128(v) => builder.append_value(i256::from_i128(*v)),
+                        AlbaTypes::U128(v) => builder.append_value(i256::from_parts(*v, 0)),
+                        other => return Err(mismatch(other))
+                    }
+                }
+                Arc::new(builder.finish())
+            },
+            DataType::FixedSizeBinary(16) => {
+                let mut builder = FixedSizeBinaryBuilder::with_capacity(rows.len(), 16);
+                for value in values{
+                    match value?{
+                        AlbaTypes::I128(v) => builder.append_value(v.to_le_bytes()),
+                        AlbaTypes::U128(v) => builder.append_value(v.to_le_bytes()),
+                        other => return Err(mismatch(other))
+                    }.map_err(arrow_error)?;
+                }
+                Arc::new(builder.finish())
+            },
+            DataType::Struct(fields) => {
+                let mut lat = Float64Builder::with_capacity(rows.len());
+                let mut lon = Float64Builder::with_capacity(rows.len());
+                for value in values{
+                    match value?{
+                        AlbaTypes::Geo((a, b)) => {
+                            lat.append_value(*a);
+                            lon.append_value(*b);
+                        },
+                        other => return Err(mismatch(other))
+                    }
+                }
+                let children : Vec<ArrayRef> = vec![Arc::new(lat.finish()), Arc::new(lon.finish())];
+                Arc::new(StructArray::try_new(fields.clone(), children, None).map_err(arrow_error)?)
+            },
+            other => return Err(Error::new(ErrorKind::InvalidInput, &format!("Unsupported Arrow type {}", other)))
+        })
+    }
+
+    #[cfg(feature = "parquet")]
+    const PARQUET_BATCH_ROWS : usize = 8192;
+
+    /// Write the rows as Parquet, converted into record batches of 8192 rows like `record_batch`. Returns the row count.
+    #[cfg(feature = "parquet")]
+    pub fn write_parquet<W : std::io::Write + Send, R : Borrow<Row>, I : IntoIterator<Item = Result<R,Error>>>(writer : W, columns : &[String], rows : I, wide : WideIntegers) -> Result<usize,Error>{
+        let mut rows = rows.into_iter();
+        let mut buffer = Vec::with_capacity(PARQUET_BATCH_ROWS);
+        let mut fill = |buffer : &mut Vec<R>| -> Result<(),Error>{
+            buffer.clear();
+            for row in rows.by_ref(){
+                buffer.push(row?);
+                if buffer.len() == PARQUET_BATCH_ROWS{
+                    break
+                }
+            }
+            Ok(())
+        };
+        fill(&mut buffer)?;
+        let schema = Arc::new(arrow_schema(columns, buffer.first().map(|r| r.borrow()), wide)?);
+        let mut writer = parquet::arrow::ArrowWriter::try_new(writer, schema.clone(), None).map_err(arrow_error)?;
+        let mut count = 0;
+        while !buffer.is_empty(){
+            writer.write(&batch(&schema, &buffer)?).map_err(arrow_error)?;
+            count += buffer.len();
+            fill(&mut buffer)?;
+        }
+        writer.close().map_err(arrow_error)?;
+        Ok(count)
+    }
+}
@@ -0,0 +1,294 @@
+use crate::{
+    albastream::{CompiledAlba, Error, ErrorKind},
+    db_response::{DBResponse, Row},
+    handler::SearchBuilder,
+    types::{AlbaTypes, ToAlbaAlbaTypes},
+};
+
+/// Mean radius of the Earth in meters, used by the haversine formula.
+pub const EARTH_RADIUS_METERS: f64 = 6_371_008.8;
+
+/// A geographic coordinate stored in a `GEO` column.
+///
+/// On the wire it is an `AlbaTypes::Geo((lat, lon))`, both in decimal degrees.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Point {
+    lat: f64,
+    lon: f64,
+}
+
+impl Point {
+    /// Creates a new point, the latitude must be in `-90..=90` and the longitude in `-180..=180`.
+    pub fn new(lat: f64, lon: f64) -> Result<Point, Error> {
+        if !lat.is_finite() || !(-90.0..=90.0).contains(&lat) {
+            return Err(Error::new(ErrorKind::InvalidInput, "The latitude must be between -90 and 90 degrees"));
+        }
+        if !lon.is_finite() || !(-180.0..=180.0).contains(&lon) {
+            return Err(Error::new(ErrorKind::InvalidInput, "The longitude must be between -180 and 180 degrees"));
+        }
+        Ok(Point { lat, lon })
+    }
+    pub fn lat(&self) -> f64 {
+        self.lat
+    }
+    pub fn lon(&self) -> f64 {
+        self.lon
+    }
+
+    /// Great-circle distance to `other` in meters, using the haversine formula.
+    pub fn distance(&self, other: &Point) -> f64 {
+        let (lat0, lat1) = (self.lat.to_radians(), other.lat.to_radians());
+        let d_lat = lat1 - lat0;
+        let d_lon = (other.lon - self.lon).to_radians();
+        let a = (d_lat / 2.0).sin().powi(2) + lat0.cos() * lat1.cos() * (d_lon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS_METERS * a.sqrt().min(1.0).asin()
+    }
+
+    /// Smallest latitude/longitude box containing every point within `radius` meters of this one.
+    pub fn bounding_box(&self, radius: f64) -> BoundingBox {
+        let angular = radius.max(0.0) / EARTH_RADIUS_METERS;
+        let lat = self.lat.to_radians();
+        let min_lat = lat - angular;
+        let max_lat = lat + angular;
+        if min_lat <= -std::f64::consts::FRAC_PI_2 || max_lat >= std::f64::consts::FRAC_PI_2 {
+            // one of the poles is inside the circle, every longitude is reachable
+            return BoundingBox {
+                min_lat: min_lat.to_degrees().max(-90.0),
+                min_lon: -180.0,
+                max_lat: max_lat.to_degrees().min(90.0),
+                max_lon: 180.0,
+            };
+        }
+        let d_lon = (angular.sin() / lat.cos()).min(1.0).asin().to_degrees();
+        let mut min_lon = self.lon - d_lon;
+        let mut max_lon = self.lon + d_lon;
+        if min_lon < -180.0 {
+            min_lon += 360.0;
+        }
+        if max_lon > 180.0 {
+            max_lon -= 360.0;
+        }
+        BoundingBox {
+            min_lat: min_lat.to_degrees(),
+            min_lon,
+            max_lat: max_lat.to_degrees(),
+            max_lon,
+        }
+    }
+}
+
+/// A latitude/longitude rectangle in decimal degrees.
+///
+/// When the box crosses the antimeridian `min_lon` is greater than `max_lon`. The server can't filter on it: a
+/// `GEO` value is compared as a whole, its latitude and longitude being out of reach of the conditions, so the box
+/// only prefilters on the client (as `NearSearch` does before computing distances) or bounds searches over
+/// separate latitude and longitude columns.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub min_lat: f64,
+    pub min_lon: f64,
+    pub max_lat: f64,
+    pub max_lon: f64,
+}
+
+impl BoundingBox {
+    /// Whether the box wraps around the antimeridian.
+    pub fn crosses_antimeridian(&self) -> bool {
+        self.min_lon > self.max_lon
+    }
+    pub fn contains(&self, point: &Point) -> bool {
+        if point.lat < self.min_lat || point.lat > self.max_lat {
+            return false;
+        }
+        if self.crosses_antimeridian() {
+            point.lon >= self.min_lon || point.lon <= self.max_lon
+        } else {
+            point.lon >= self.min_lon && point.lon <= self.max_lon
+        }
+    }
+}
+
+impl ToAlbaAlbaTypes for Point {
+    fn to_alba_alba_types(&self) -> AlbaTypes {
+        AlbaTypes::Geo((self.lat, self.lon))
+    }
+}
+
+impl From<Point> for AlbaTypes {
+    fn from(point: Point) -> AlbaTypes {
+        AlbaTypes::Geo((point.lat, point.lon))
+    }
+}
+
+impl TryFrom<&AlbaTypes> for Point {
+    type Error = Error;
+    fn try_from(value: &AlbaTypes) -> Result<Point, Error> {
+        match value {
+            AlbaTypes::Geo((lat, lon)) => Point::new(*lat, *lon),
+            _ => Err(Error::new(ErrorKind::InvalidInput, "The value is not a Geo value")),
+        }
+    }
+}
+
+#[cfg(feature = "geo-types")]
+impl TryFrom<geo_types::Point<f64>> for Point {
+    type Error = Error;
+    fn try_from(point: geo_types::Point<f64>) -> Result<Point, Error> {
+        Point::new(point.y(), point.x())
+    }
+}
+
+#[cfg(feature = "geo-types")]
+impl From<Point> for geo_types::Point<f64> {
+    fn from(point: Point) -> geo_types::Point<f64> {
+        geo_types::Point::new(point.lon, point.lat)
+    }
+}
+
+/// A `Search` restricted to the rows whose `GEO` column lies within a distance of a point.
+///
+/// TytoDB has no geospatial operator, so the distance check happens on the client: `finish` compiles the
+/// search and `filter` drops the rows farther than the radius from the response, sorting the remaining ones
+/// by proximity. No bounding box condition is sent since the server defines no ordering of `GEO` values, so
+/// the conditions of the search should narrow the rows down.
+#[derive(Debug)]
+pub struct NearSearch {
+    search: SearchBuilder,
+    column: String,
+    center: Point,
+    radius: f64,
+}
+
+impl NearSearch {
+    /// Wraps `search`, keeping only the rows whose `column` is within `radius` meters of `center`.
+    ///
+    /// The column is added to the projection of the search if it isn't there yet.
+    pub fn new(search: SearchBuilder, column: String, center: Point, radius: f64) -> Self {
+        let mut search = search;
+        if !search.column_names.contains(&column) {
+            search = search.add_column_name(column.clone());
+        }
+        NearSearch { search, column, center, radius }
+    }
+
+    pub fn center(&self) -> Point {
+        self.center
+    }
+    pub fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    /// Finish the builder, returning the compiled `Search` bytes in the `CompiledAlba` type.
+    pub fn finish(&self) -> Result<CompiledAlba, Error> {
+        self.search.request()
+    }
+
+    /// Drops the rows outside of the circle and sorts the remaining ones from the nearest to the farthest.
+    ///
+    /// The post-processing of the wrapped search (`order_by`, `limit`, ...) is applied afterwards, the distance
+    /// being the tie-breaker of its ordering.
+    pub fn filter(&self, response: DBResponse) -> Result<DBResponse, Error> {
+        let rows = self.filter_with_distance(response)?.into_iter().map(|(_, r)| r).collect();
+        self.search.post_process(DBResponse::new(rows))
+    }
+
+    /// Same as `filter` without the post-processing of the search, but keeps the distance in meters of every row to the center.
+    pub fn filter_with_distance(&self, response: DBResponse) -> Result<Vec<(f64, Row)>, Error> {
+        let index = match self.search.column_names.iter().position(|c| *c == self.column) {
+            Some(i) => i,
+            None => return Err(Error::new(ErrorKind::InvalidInput, "The geo column is not part of the search projection")),
+        };
+        let mut rows = Vec::with_capacity(response.row_list.len());
+        let bounds = self.center.bounding_box(self.radius);
+        for row in response.row_list {
+            let point = match row.0.get(index) {
+                Some(AlbaTypes::Geo((lat, lon))) => Point { lat: *lat, lon: *lon },
+                Some(_) => return Err(Error::new(ErrorKind::InvalidInput, "The geo column returned a non Geo value")),
+                None => return Err(Error::new(ErrorKind::UnexpectedEof, "The row is shorter than the search projection")),
+            };
+            if !bounds.contains(&point) {
+                continue;
+            }
+            let distance = self.center.distance(&point);
+            if distance <= self.radius {
+                rows.push((distance, row));
+            }
+        }
+        rows.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(lat: f64, lon: f64) -> Point {
+        Point::new(lat, lon).unwrap()
+    }
+
+    #[test]
+    fn coordinates_are_validated() {
+        assert!(Point::new(90.0, -180.0).is_ok());
+        assert!(Point::new(-90.0, 180.0).is_ok());
+        for (lat, lon) in [(90.5, 0.0), (-91.0, 0.0), (0.0, 180.1), (0.0, -181.0), (f64::NAN, 0.0), (0.0, f64::INFINITY)] {
+            assert_eq!(Point::new(lat, lon).unwrap_err().kind(), ErrorKind::InvalidInput);
+        }
+        assert!(Point::try_from(&AlbaTypes::Geo((100.0, 0.0))).is_err());
+        assert!(Point::try_from(&AlbaTypes::F64(1.0)).is_err());
+    }
+
+    #[test]
+    fn distances_follow_the_great_circle() {
+        let paris = point(48.8566, 2.3522);
+        let london = point(51.5074, -0.1278);
+        assert!((paris.distance(&london) - 343_556.5).abs() < 1.0);
+        assert_eq!(paris.distance(&london), london.distance(&paris));
+        assert_eq!(paris.distance(&paris), 0.0);
+        let origin = point(0.0, 0.0);
+        let quarter = std::f64::consts::FRAC_PI_2 * EARTH_RADIUS_METERS;
+        assert!((origin.distance(&point(90.0, 0.0)) - quarter).abs() < 1e-6);
+        assert!((origin.distance(&point(0.0, 180.0)) - 2.0 * quarter).abs() < 1e-6);
+        // across the antimeridian, not around the globe
+        assert!(point(0.0, 179.5).distance(&point(0.0, -179.5)) < 112_000.0);
+    }
+
+    #[test]
+    fn boxes_wrap_around_the_antimeridian() {
+        let center = point(10.0, 179.8);
+        let bounds = center.bounding_box(50_000.0);
+        assert!(bounds.crosses_antimeridian());
+        assert!(bounds.max_lon < -179.0 && bounds.min_lon > 179.0);
+        assert!(bounds.contains(&point(10.0, -179.9)));
+        assert!(bounds.contains(&point(10.1, 179.9)));
+        assert!(!bounds.contains(&point(10.0, 0.0)));
+        assert!(!bounds.contains(&point(11.0, 179.9)));
+        let inside = point(10.0, -179.8);
+        assert!(center.distance(&inside) < 50_000.0 && bounds.contains(&inside));
+
+        let bounds = point(10.0, 0.0).bounding_box(50_000.0);
+        assert!(!bounds.crosses_antimeridian());
+        assert!(!bounds.contains(&point(10.0, 180.0)));
+    }
+
+    #[test]
+    fn boxes_around_a_pole_cover_every_longitude() {
+        let bounds = point(89.9, 45.0).bounding_box(50_000.0);
+        assert_eq!((bounds.min_lon, bounds.max_lon, bounds.max_lat), (-180.0, 180.0, 90.0));
+        assert!(bounds.contains(&point(89.9, -135.0)));
+        let bounds = point(-89.99, 0.0).bounding_box(10_000.0);
+        assert_eq!((bounds.min_lon, bounds.max_lon, bounds.min_lat), (-180.0, 180.0, -90.0));
+        assert!(!bounds.contains(&point(-89.0, 0.0)));
+    }
+
+    #[test]
+    fn near_searches_keep_the_rows_within_the_radius() {
+        let search = SearchBuilder::new().add_container("places".to_string()).add_column_name("at".to_string());
+        let near = NearSearch::new(search, "at".to_string(), point(0.0, 179.9), 30_000.0);
+        let rows = [(0.0, -179.95), (0.0, 179.5), (0.1, 179.9)].into_iter().map(|(lat, lon)| Row::new(vec![AlbaTypes::Geo((lat, lon))])).collect();
+        let kept = near.filter_with_distance(DBResponse::new(rows)).unwrap();
+        let kept: Vec<&AlbaTypes> = kept.iter().map(|(_, r)| &r.0[0]).collect();
+        // nearest first, the row across the antimeridian being 16.7km away
+        assert_eq!(kept, vec![&AlbaTypes::Geo((0.1, 179.9)), &AlbaTypes::Geo((0.0, -179.95))]);
+    }
+}
@@ -1,19 +1,69 @@
 
 
-use crate::{albastream::{CompiledAlba, Error}, commands::{AlbaContainer, Batch, BatchCreateRows, Commands, Commit, CreateContainer, CreateRow, DeleteContainer, DeleteRow, Rollback}, logical_operators::LogicalOperator, types::AlbaTypes};
+use std::{cmp::Ordering, collections::HashSet};
+
+use crate::{albastream::{CompiledAlba, Error, ErrorKind}, db_response::DBResponse, commands::{AlbaContainer, Batch, BatchCreateRows, Commands, Commit, Condition, Conditions, CreateContainer, CreateRow, DeleteContainer, DeleteRow, Rollback}, logical_operators::{LogicalOperator, Lowered, lower}, types::AlbaTypes};
 use crate::commands::Search;
 use crate::commands::EditRow;
 
+/// Direction of an `order_by` clause.
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Default)]
+pub enum Order{
+    #[default]
+    Asc,
+    Desc
+}
+
 /// API for building a `Search` structure
-#[derive(Debug,Default)]
+///
+/// `order_by`, `limit`, `offset`, `distinct` and the `filters` are not part of the TytoDB protocol, they are applied
+/// on the client by `post_process` over the response of the compiled search. The clients' `search` does both; a
+/// search with filters doesn't `finish`, since its response alone would hold the rows the filters exclude.
+#[derive(Debug,Clone,Default)]
 pub struct SearchBuilder{
     pub container: AlbaContainer,
     pub column_names: Vec<String>,
-    pub conditions: (Vec<(String,LogicalOperator,AlbaTypes)>,Vec<(u8,char)>)
+    pub conditions: Conditions,
+    pub order_by: Vec<(String,Order)>,
+    pub limit: Option<usize>,
+    pub offset: usize,
+    pub distinct: bool,
+    pub filters: Vec<Condition>,
+    pub(crate) joined: Option<char>,
+    pub(crate) error: Option<Error>
 }
 
 pub(crate) trait BatchingItem{
     fn into_batching_item(self) -> Commands;
+    /// The error the item would fail to compile with, kept by `BatchBuilder::push`.
+    fn batching_error(&self) -> Option<Error>{
+        None
+    }
+}
+
+/// Whether a group of conditions joined with AND (`and`) or OR, lowered from one operator, can be spliced into a
+/// chain whose `gates` are given and that it joins with `logic`. The chain has no grouping, so the splice only
+/// keeps its meaning when every gate of the chain is the gate of the group.
+fn check_splice(mut gates: impl Iterator<Item = char>, empty: bool, logic: bool, and: bool) -> Result<(),Error>{
+    let gate = if and {'a'} else {'o'};
+    if empty || (logic == and && gates.all(|g| g.to_ascii_lowercase() == gate)){
+        return Ok(())
+    }
+    Err(Error::new(ErrorKind::InvalidInput, &format!(
+        "The operator is lowered into conditions joined with {0}, it can only be part of a chain joined only with {0}",
+        if and {"AND"} else {"OR"}
+    )))
+}
+
+/// Whether a condition can be joined with `logic` to a chain that holds a spliced group (`joined`), see `check_splice`.
+fn check_join(joined: Option<char>, empty: bool, logic: bool) -> Result<(),Error>{
+    match joined{
+        Some(gate) if !empty && logic != (gate == 'a') => Err(Error::new(ErrorKind::InvalidInput, &format!(
+            "The chain holds an operator lowered into conditions joined with {0}, it can only be joined with {0}",
+            if gate == 'a' {"AND"} else {"OR"}
+        ))),
+        _ => Ok(())
+    }
 }
 
 impl SearchBuilder {
@@ -33,7 +83,16 @@ impl SearchBuilder {
     }
     /// Add a new condition to the condition chain of the `Search` structure being built.
     /// logic -> true = AND, false = OR 
-    pub fn add_conditions(mut self, condition: (String,LogicalOperator,AlbaTypes), logic: bool)-> Self{
+    pub fn add_conditions(self, condition: (String,LogicalOperator,AlbaTypes), logic: bool)-> Self{
+        if !condition.1.is_native(){
+            return self.add_condition_values(condition.0, condition.1, vec![condition.2], logic)
+        }
+        self.push_condition(condition, logic)
+    }
+    fn push_condition(mut self, condition: Condition, logic: bool)-> Self{
+        if let Err(e) = check_join(self.joined, self.conditions.0.is_empty(), logic){
+            self.error.get_or_insert(e);
+        }
         self.conditions.0.push(condition);
         let l = self.conditions.0.len() as u8;
         if l == 1 {
@@ -43,30 +102,200 @@ impl SearchBuilder {
         }
         self
     }
+    /// Push the conditions an operator was lowered into, joined with AND (`and`) or OR, see `check_splice`.
+    fn splice(mut self, mut conditions: Vec<Condition>, and: bool, logic: bool)-> Self{
+        if conditions.len() == 1{
+            return self.push_condition(conditions.remove(0), logic)
+        }
+        if let Err(e) = check_splice(self.conditions.1.iter().skip(1).map(|(_,g)| *g), self.conditions.0.is_empty(), logic, and){
+            self.error.get_or_insert(e);
+            return self
+        }
+        for c in conditions{
+            self = self.push_condition(c, and);
+        }
+        self.joined = Some(if and {'a'} else {'o'});
+        self
+    }
+    /// Add a condition taking a list of values (`In`, `NotIn`, `Between`) to the condition chain of the `Search` structure being built.
+    /// logic -> true = AND, false = OR 
+    ///
+    /// The operators TytoDB doesn't understand are lowered into native ones (see `logical_operators::lower`), the
+    /// negated string operators become `filters` and must be joined with AND. The chain has no grouping, so an
+    /// operator lowered into several conditions (a non string `In`, `NotIn`, `Between`) only joins a chain using the
+    /// operator of its conditions (OR for `In`, AND otherwise), and the chain keeps it; `finish` fails otherwise.
+    pub fn add_condition_values(mut self, column: String, operator: LogicalOperator, values: Vec<AlbaTypes>, logic: bool)-> Self{
+        match lower(column, operator, values){
+            Ok(Lowered::All(conditions)) => self = self.splice(conditions, true, logic),
+            Ok(Lowered::Any(conditions)) => self = self.splice(conditions, false, logic),
+            Ok(Lowered::Filter(condition)) => {
+                if !logic && !self.conditions.0.is_empty(){
+                    self.error.get_or_insert(Error::new(ErrorKind::InvalidInput, "A client-side filter can only be joined with AND"));
+                }
+                self.filters.push(condition);
+            },
+            Err(e) => {
+                self.error.get_or_insert(e);
+            }
+        }
+        self
+    }
+    /// Sort the rows by `column`, calling it again adds a tie-breaker to the previous ones.
+    ///
+    /// The column doesn't need to be in the projection, it is fetched and stripped from the rows by `post_process`.
+    pub fn order_by(mut self, column: String, order: Order)-> Self{
+        self.order_by.push((column,order));
+        self
+    }
+    /// Keep at most `limit` rows.
+    pub fn limit(mut self, limit: usize)-> Self{
+        self.limit = Some(limit);
+        self
+    }
+    /// Skip the first `offset` rows.
+    pub fn offset(mut self, offset: usize)-> Self{
+        self.offset = offset;
+        self
+    }
+    /// Drop the repeated rows, keeping the first occurrence of each.
+    pub fn distinct(mut self)-> Self{
+        self.distinct = true;
+        self
+    }
+
+    /// The columns actually requested to the server: the projection plus the `order_by` columns missing from it.
+    pub(crate) fn projection(&self) -> Result<Vec<String>,Error>{
+        if let Some(e) = &self.error{
+            return Err(e.clone())
+        }
+        if !self.filters.is_empty() && self.conditions.1.iter().any(|(_,l)| *l == 'o' || *l == 'O'){
+            return Err(Error::new(ErrorKind::InvalidInput, "Client-side filters require a condition chain joined only by AND"))
+        }
+        let mut columns = self.column_names.clone();
+        if self.order_by.is_empty() && self.filters.is_empty(){
+            return Ok(columns)
+        }
+        if columns.is_empty(){
+            return Err(Error::new(ErrorKind::InvalidInput, "order_by and the filters require the columns of the search to be listed"))
+        }
+        for column in self.order_by.iter().map(|o| &o.0).chain(self.filters.iter().map(|f| &f.0)){
+            if !columns.contains(column){
+                columns.push(column.clone());
+            }
+        }
+        Ok(columns)
+    }
+
+    /// Apply the `filters`, `order_by`, `distinct`, `offset` and `limit` to the response of the compiled search.
+    ///
+    /// The filters run first, then `distinct`, then the ordering (a stable sort), then `offset` and `limit`.
+    pub fn post_process(&self, response: DBResponse) -> Result<DBResponse,Error>{
+        if self.order_by.is_empty() && self.limit.is_none() && self.offset == 0 && !self.distinct && self.filters.is_empty(){
+            return Ok(response)
+        }
+        let projection = self.projection()?;
+        let visible = self.column_names.len();
+        let mut keys = Vec::with_capacity(self.order_by.len());
+        for (column,order) in self.order_by.iter(){
+            match projection.iter().position(|c| c == column){
+                Some(index) => keys.push((index,*order)),
+                None => return Err(Error::new(ErrorKind::InvalidInput, "An order_by column is missing from the search projection"))
+            }
+        }
+        let mut rows = response.row_list;
+        if rows.iter().any(|r| r.0.len() < projection.len()){
+            return Err(Error::new(ErrorKind::UnexpectedEof, "A row is shorter than the search projection"))
+        }
+        for (column,operator,operand) in self.filters.iter(){
+            let index = match projection.iter().position(|c| c == column){
+                Some(index) => index,
+                None => return Err(Error::new(ErrorKind::InvalidInput, "A filter column is missing from the search projection"))
+            };
+            let mut failed = None;
+            rows.retain(|r| match operator.matches(&r.0[index], operand){
+                Some(keep) => keep,
+                None => {
+                    failed = Some(Error::new(ErrorKind::InvalidInput, "A client-side filter can't be evaluated on this value"));
+                    false
+                }
+            });
+            if let Some(e) = failed{
+                return Err(e)
+            }
+        }
+        if self.distinct{
+            let mut seen = HashSet::new();
+            rows.retain(|r| {
+                let width = if visible == 0 {r.0.len()} else {visible};
+                seen.insert(r.0[..width].iter().flat_map(|v| v.as_bytes()).collect::<Vec<u8>>())
+            });
+        }
+        if !keys.is_empty(){
+            rows.sort_by(|a,b| {
+                for (index,order) in keys.iter(){
+                    let ordering = a.0[*index].total_cmp(&b.0[*index]);
+                    let ordering = if *order == Order::Desc {ordering.reverse()} else {ordering};
+                    if ordering != Ordering::Equal{
+                        return ordering
+                    }
+                }
+                Ordering::Equal
+            });
+        }
+        let rows = rows.into_iter()
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX))
+            .map(|mut r| {
+                if projection.len() > visible{
+                    r.0.truncate(visible);
+                }
+                r
+            })
+            .collect();
+        Ok(DBResponse::new(rows))
+    }
+
+    /// The compiled search to send when its response goes through `post_process`, the filters included.
+    pub fn request(&self) -> Result<CompiledAlba,Error>{
+        Ok(Commands::Search(Search{
+            container: self.container.clone(),
+            conditions: self.conditions.clone(),
+            col_nam: self.projection()?
+        }).compile()? as CompiledAlba)
+    }
+    fn check_unfiltered(&self) -> Result<(),Error>{
+        if self.filters.is_empty(){
+            return Ok(())
+        }
+        Err(Error::new(ErrorKind::InvalidInput, "The search has client-side filters, send it with the client's `search` or `request` and `post_process`"))
+    }
     /// Finish the builder, returning the compiled `Search` bytes in the `CompiledAlba` type.
+    ///
+    /// Fails when the search has `filters`, see `request`.
     pub fn finish(self) -> Result<CompiledAlba,Error>{
+        self.check_unfiltered()?;
+        let col_nam = self.projection()?;
         Ok(Commands::Search(Search{
             container: self.container,
             conditions: self.conditions,
-            col_nam: self.column_names
+            col_nam
         }).compile()? as CompiledAlba)
     }
     /// Finish the builder, returning the compiled `Search` bytes in the `CompiledAlba` type.
     /// 
     /// The difference between this method and the `finish` is that by using this one you can compile multiple times to recicle the builder.
     pub fn cloned_finish(&self) -> Result<CompiledAlba,Error>{
-        Ok(Commands::Search(Search{
-            container: self.container.clone(),
-            conditions: self.conditions.clone(),
-            col_nam: self.column_names.clone()
-        }).compile()? as CompiledAlba)
+        self.check_unfiltered()?;
+        self.request()
     }
 }
 #[derive(Debug,Clone,Default)]
 pub struct EditRowBuilder{
     pub (crate) container : String,
     pub (crate) changes : (Vec<String>,Vec<AlbaTypes>),
-    pub (crate) conditions : (Vec<(String,LogicalOperator,AlbaTypes)>,Vec<(u8,char)>)
+    pub (crate) conditions : Conditions,
+    pub (crate) joined : Option<char>,
+    pub (crate) error : Option<Error>
 }
 impl EditRowBuilder {
     pub fn new() -> Self {
@@ -85,7 +314,16 @@ impl EditRowBuilder {
     }
     /// Add a new condition to the condition chain of the `EditRow` structure being built.
     /// logic -> true = AND, false = OR 
-    pub fn add_conditions(mut self, condition: (String,LogicalOperator,AlbaTypes), logic: bool)-> Self{
+    pub fn add_conditions(self, condition: (String,LogicalOperator,AlbaTypes), logic: bool)-> Self{
+        if !condition.1.is_native(){
+            return self.add_condition_values(condition.0, condition.1, vec![condition.2], logic)
+        }
+        self.push_condition(condition, logic)
+    }
+    fn push_condition(mut self, condition: Condition, logic: bool)-> Self{
+        if let Err(e) = check_join(self.joined, self.conditions.0.is_empty(), logic){
+            self.error.get_or_insert(e);
+        }
         self.conditions.0.push(condition);
         let l = self.conditions.0.len() as u8;
         if l > 1{
@@ -93,8 +331,45 @@ impl EditRowBuilder {
         }
         self
     }
+    /// Push the conditions an operator was lowered into, joined with AND (`and`) or OR, see `check_splice`.
+    fn splice(mut self, mut conditions: Vec<Condition>, and: bool, logic: bool)-> Self{
+        if conditions.len() == 1{
+            return self.push_condition(conditions.remove(0), logic)
+        }
+        if let Err(e) = check_splice(self.conditions.1.iter().map(|(_,g)| *g), self.conditions.0.is_empty(), logic, and){
+            self.error.get_or_insert(e);
+            return self
+        }
+        for c in conditions{
+            self = self.push_condition(c, and);
+        }
+        self.joined = Some(if and {'a'} else {'o'});
+        self
+    }
+    /// Add a condition taking a list of values (`In`, `NotIn`, `Between`) to the condition chain of the `EditRow` structure being built.
+    /// logic -> true = AND, false = OR 
+    ///
+    /// The operators TytoDB doesn't understand are lowered into native ones (see `logical_operators::lower`), the
+    /// negated string operators can't be lowered and make `finish` fail. An operator lowered into several conditions
+    /// only joins a chain using the operator of its conditions, see `SearchBuilder::add_condition_values`.
+    pub fn add_condition_values(mut self, column: String, operator: LogicalOperator, values: Vec<AlbaTypes>, logic: bool)-> Self{
+        match lower(column, operator, values){
+            Ok(Lowered::All(conditions)) => self = self.splice(conditions, true, logic),
+            Ok(Lowered::Any(conditions)) => self = self.splice(conditions, false, logic),
+            Ok(Lowered::Filter(_)) => {
+                self.error.get_or_insert(Error::new(ErrorKind::InvalidInput, "The negated string operators are only supported by searches"));
+            },
+            Err(e) => {
+                self.error.get_or_insert(e);
+            }
+        }
+        self
+    }
     /// Finish the builder, returning the compiled `Search` bytes in the `CompiledAlba` type.
     pub fn finish(self) -> Result<CompiledAlba,Error>{
+        if let Some(e) = self.error{
+            return Err(e)
+        }
         let col_nam : Vec<String> = self.changes.0;
         let col_val : Vec<AlbaTypes> = self.changes.1;
         Ok(Commands::EditRow(EditRow{
@@ -109,6 +384,9 @@ impl EditRowBuilder {
     /// 
     /// The difference between this method and the `finish` is that by using this one you can compile multiple times to recicle the builder.
     pub fn cloned_finish(&self) -> Result<CompiledAlba,Error>{
+        if let Some(e) = &self.error{
+            return Err(e.clone())
+        }
         let col_nam : Vec<String> = self.changes.0.clone();
         let col_val : Vec<AlbaTypes> = self.changes.1.clone();
         Ok(Commands::EditRow(EditRow{
@@ -126,7 +404,9 @@ impl EditRowBuilder {
 #[derive(Debug,Clone,Default)]
 pub struct DeleteRowBuilder{
     pub(crate) container : String,
-    pub(crate) conditions : (Vec<(String,LogicalOperator,AlbaTypes)>,Vec<(usize,char)>)
+    pub(crate) conditions : Conditions<usize>,
+    pub(crate) joined : Option<char>,
+    pub(crate) error : Option<Error>
 }
 impl DeleteRowBuilder {
     pub fn new() -> Self {
@@ -140,7 +420,16 @@ impl DeleteRowBuilder {
     }
     /// Add a new condition to the condition chain of the `DeleteRow` structure being built.
     /// logic -> true = AND, false = OR 
-    pub fn add_conditions(mut self, condition: (String,LogicalOperator,AlbaTypes), logic: bool)-> Self{
+    pub fn add_conditions(self, condition: (String,LogicalOperator,AlbaTypes), logic: bool)-> Self{
+        if !condition.1.is_native(){
+            return self.add_condition_values(condition.0, condition.1, vec![condition.2], logic)
+        }
+        self.push_condition(condition, logic)
+    }
+    fn push_condition(mut self, condition: Condition, logic: bool)-> Self{
+        if let Err(e) = check_join(self.joined, self.conditions.0.is_empty(), logic){
+            self.error.get_or_insert(e);
+        }
         self.conditions.0.push(condition);
         let l = self.conditions.0.len();
         if l > 1{
@@ -148,8 +437,45 @@ impl DeleteRowBuilder {
         }
         self
     }
+    /// Push the conditions an operator was lowered into, joined with AND (`and`) or OR, see `check_splice`.
+    fn splice(mut self, mut conditions: Vec<Condition>, and: bool, logic: bool)-> Self{
+        if conditions.len() == 1{
+            return self.push_condition(conditions.remove(0), logic)
+        }
+        if let Err(e) = check_splice(self.conditions.1.iter().map(|(_,g)| *g), self.conditions.0.is_empty(), logic, and){
+            self.error.get_or_insert(e);
+            return self
+        }
+        for c in conditions{
+            self = self.push_condition(c, and);
+        }
+        self.joined = Some(if and {'a'} else {'o'});
+        self
+    }
+    /// Add a condition taking a list of values (`In`, `NotIn`, `Between`) to the condition chain of the `DeleteRow` structure being built.
+    /// logic -> true = AND, false = OR 
+    ///
+    /// The operators TytoDB doesn't understand are lowered into native ones (see `logical_operators::lower`), the
+    /// negated string operators can't be lowered and make `finish` fail. An operator lowered into several conditions
+    /// only joins a chain using the operator of its conditions, see `SearchBuilder::add_condition_values`.
+    pub fn add_condition_values(mut self, column: String, operator: LogicalOperator, values: Vec<AlbaTypes>, logic: bool)-> Self{
+        match lower(column, operator, values){
+            Ok(Lowered::All(conditions)) => self = self.splice(conditions, true, logic),
+            Ok(Lowered::Any(conditions)) => self = self.splice(conditions, false, logic),
+            Ok(Lowered::Filter(_)) => {
+                self.error.get_or_insert(Error::new(ErrorKind::InvalidInput, "The negated string operators are only supported by searches"));
+            },
+            Err(e) => {
+                self.error.get_or_insert(e);
+            }
+        }
+        self
+    }
     /// Finish the builder, returning the compiled `DeleteRow` bytes in the `CompiledAlba` type.
     pub fn finish(self) -> Result<CompiledAlba,Error>{
+        if let Some(e) = self.error{
+            return Err(e)
+        }
         Ok(Commands::DeleteRow(DeleteRow{
             container: self.container,
             conditions: Some(self.conditions)
@@ -159,6 +485,9 @@ impl DeleteRowBuilder {
     /// 
     /// The difference between this method and the `finish` is that by using this one you can compile multiple times to recicle the builder.
     pub fn cloned_finish(&self) -> Result<CompiledAlba,Error>{
+        if let Some(e) = &self.error{
+            return Err(e.clone())
+        }
         Ok(Commands::DeleteRow(DeleteRow{
             container: self.container.clone(),
             conditions: Some(self.conditions.clone())
@@ -391,7 +720,8 @@ impl BatchCreateRowsBuilder {
 
 pub struct BatchBuilder{
     pub transaction : bool,
-    pub(crate)value : Vec<Commands> 
+    pub(crate)value : Vec<Commands>,
+    pub(crate) error : Option<Error>
 }
 
 impl BatchBuilder {
@@ -406,17 +736,25 @@ impl BatchBuilder {
     } 
 
     /// Insert a operation into the batching
+    ///
+    /// A builder that would fail to compile (see `SearchBuilder::add_condition_values`) makes `finish` fail.
     #[allow(private_bounds)] 
     pub fn push<VERYNICEITEM:BatchingItem>(mut self, bin : VERYNICEITEM) -> Self{
+        if let Some(e) = bin.batching_error(){
+            self.error.get_or_insert(e);
+        }
         self.value.push(bin.into_batching_item());
         self
     }
     
     pub fn finish(self) -> Result<CompiledAlba,Error>{
+        if let Some(e) = self.error{
+            return Err(e)
+        }
         self.into_batching_item().compile()
     }
     pub fn cloned_finish(self) -> Result<CompiledAlba,Error>{
-        self.clone().into_batching_item().compile()
+        self.clone().finish()
     }
 }
 impl BatchingItem for BatchBuilder{
@@ -426,6 +764,9 @@ impl BatchingItem for BatchBuilder{
             commands: self.value
         })  
     }
+    fn batching_error(&self) -> Option<Error>{
+        self.error.clone()
+    }
 }
 impl BatchingItem for Commands{
     fn into_batching_item(self) -> Commands {
@@ -440,6 +781,12 @@ impl BatchingItem for SearchBuilder {
             col_nam: self.column_names
         })
     }
+    fn batching_error(&self) -> Option<Error>{
+        if !self.filters.is_empty(){
+            return Some(Error::new(ErrorKind::InvalidInput, "The client-side filters of a search can't be batched"))
+        }
+        self.error.clone()
+    }
 }
 
 impl BatchingItem for EditRowBuilder {
@@ -453,6 +800,9 @@ impl BatchingItem for EditRowBuilder {
             col_val
         })
     }
+    fn batching_error(&self) -> Option<Error>{
+        self.error.clone()
+    }
 }
 
 impl BatchingItem for DeleteRowBuilder {
@@ -462,6 +812,9 @@ impl BatchingItem for DeleteRowBuilder {
             conditions: Some(self.conditions)
         })
     }
+    fn batching_error(&self) -> Option<Error>{
+        self.error.clone()
+    }
 }
 
 impl BatchingItem for DeleteContainerBuilder {
@@ -517,3 +870,99 @@ impl BatchingItem for BatchCreateRowsBuilder {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn search() -> SearchBuilder {
+        SearchBuilder::new().add_container("c".into()).add_column_name("id".into())
+    }
+    fn condition(column: &str, operator: LogicalOperator, value: AlbaTypes) -> Condition {
+        (column.to_string(), operator, value)
+    }
+
+    #[test]
+    fn lowered_groups_join_uniform_chains() {
+        let any = search()
+            .add_condition_values("x".into(), LogicalOperator::In, vec![AlbaTypes::U8(1), AlbaTypes::U8(2)], true)
+            .add_conditions(condition("y", LogicalOperator::Equal, AlbaTypes::U8(3)), false);
+        assert!(any.cloned_finish().is_ok());
+        assert_eq!(any.conditions.1, vec![(0, 'a'), (1, 'o'), (2, 'o')]);
+
+        let all = search()
+            .add_conditions(condition("s", LogicalOperator::Equal, AlbaTypes::U8(0)), true)
+            .add_condition_values("id".into(), LogicalOperator::Between, vec![AlbaTypes::U8(1), AlbaTypes::U8(9)], true);
+        assert!(all.cloned_finish().is_ok());
+        assert_eq!(all.conditions.1, vec![(0, 'a'), (1, 'a'), (2, 'a')]);
+
+        let strings = search()
+            .add_conditions(condition("s", LogicalOperator::Equal, AlbaTypes::U8(0)), false)
+            .add_condition_values("t".into(), LogicalOperator::In, vec![AlbaTypes::String("a".into()), AlbaTypes::String("b".into())], true);
+        assert!(strings.cloned_finish().is_ok());
+    }
+
+    #[test]
+    fn lowered_groups_reject_mixed_chains() {
+        let in_after_and = search()
+            .add_conditions(condition("a", LogicalOperator::Equal, AlbaTypes::U8(0)), true)
+            .add_condition_values("x".into(), LogicalOperator::In, vec![AlbaTypes::U8(1), AlbaTypes::U8(2)], true);
+        assert_eq!(in_after_and.cloned_finish().unwrap_err().kind(), ErrorKind::InvalidInput);
+
+        let between_after_or = search()
+            .add_conditions(condition("s", LogicalOperator::Equal, AlbaTypes::U8(0)), true)
+            .add_condition_values("id".into(), LogicalOperator::Between, vec![AlbaTypes::U8(1), AlbaTypes::U8(9)], false);
+        assert_eq!(between_after_or.cloned_finish().unwrap_err().kind(), ErrorKind::InvalidInput);
+
+        let and_after_in = search()
+            .add_condition_values("x".into(), LogicalOperator::In, vec![AlbaTypes::U8(1), AlbaTypes::U8(2)], true)
+            .add_conditions(condition("a", LogicalOperator::Equal, AlbaTypes::U8(0)), true);
+        assert_eq!(and_after_in.cloned_finish().unwrap_err().kind(), ErrorKind::InvalidInput);
+
+        let delete = DeleteRowBuilder::new()
+            .put_container("c".into())
+            .add_conditions(condition("a", LogicalOperator::Equal, AlbaTypes::U8(0)), true)
+            .add_conditions(condition("b", LogicalOperator::Equal, AlbaTypes::U8(0)), false)
+            .add_condition_values("x".into(), LogicalOperator::NotIn, vec![AlbaTypes::U8(1), AlbaTypes::U8(2)], true);
+        assert_eq!(delete.cloned_finish().unwrap_err().kind(), ErrorKind::InvalidInput);
+
+        let edit = EditRowBuilder::new()
+            .put_container("c".into())
+            .edit_column("a".into(), AlbaTypes::U8(1))
+            .add_condition_values("x".into(), LogicalOperator::In, vec![AlbaTypes::U8(1), AlbaTypes::U8(2)], true)
+            .add_conditions(condition("a", LogicalOperator::Equal, AlbaTypes::U8(0)), true);
+        assert_eq!(edit.cloned_finish().unwrap_err().kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn batches_keep_the_errors_of_their_builders() {
+        let rejected = search()
+            .add_conditions(condition("a", LogicalOperator::Equal, AlbaTypes::U8(0)), true)
+            .add_condition_values("x".into(), LogicalOperator::In, vec![AlbaTypes::U8(1), AlbaTypes::U8(2)], true);
+        let batch = BatchBuilder::new().push(search()).push(rejected);
+        assert_eq!(batch.finish().unwrap_err().kind(), ErrorKind::InvalidInput);
+        assert!(BatchBuilder::new().push(search()).finish().is_ok());
+    }
+
+    #[test]
+    fn angle_brackets_are_not_between() {
+        assert_eq!(LogicalOperator::from_spelling("<>"), None);
+        assert_eq!(LogicalOperator::from_spelling("between"), Some(LogicalOperator::Between));
+    }
+
+    #[test]
+    fn filtered_searches_only_compile_as_requests() {
+        let search = SearchBuilder::new()
+            .add_container("nice_container".to_string())
+            .add_column_name("s".to_string())
+            .add_condition_values("s".to_string(), LogicalOperator::NotStringContains, vec![AlbaTypes::String("pp".to_string())], true);
+        assert_eq!(search.cloned_finish().unwrap_err().kind(), ErrorKind::InvalidInput);
+        assert!(search.request().is_ok());
+        let rows = DBResponse::new(vec![
+            crate::db_response::Row::new(vec![AlbaTypes::String("apple".to_string())]),
+            crate::db_response::Row::new(vec![AlbaTypes::String("pear".to_string())]),
+        ]);
+        assert_eq!(search.post_process(rows).unwrap().row_list.len(), 1);
+        assert_eq!(search.finish().unwrap_err().kind(), ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,444 @@
+use std::io::{BufRead, BufReader, Lines, Read};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde_json::{Map, Value};
+
+use crate::{
+    albastream::{Error, ErrorKind},
+    bulk::{BulkInsert, BulkReport},
+    types::{AlbaTypes, BIGINT, BOOL, FLOAT, GEO, MEDIUM_STRING, NANO_INT, SHORT, UBIGINT, column_type_name, value_type_id},
+};
+
+/// The source formats of an `Importer`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Comma separated values, the first record being the header naming the fields.
+    Csv,
+    /// One JSON object per line, blank lines are skipped.
+    JsonLines,
+}
+
+/// An import of CSV or JSON Lines into a container, see `Client::import`.
+///
+/// With a declared `schema` the rows hold exactly the schema columns, each parsed as its column type. Without one
+/// the columns are the fields of the source (the CSV header or the keys of the first JSON object), parsed as their
+/// `hint` type or as the type inferred from their first value: integers, floats, `true`/`false` and strings. The
+/// later values of the column that don't parse as that type are rejected, so hint the columns whose first value
+/// doesn't tell their type (e.g. floats starting with `1`, or text starting with a number). Field names map to the
+/// column of the same name unless `map_field` says otherwise.
+///
+/// Values are parsed from text as:
+/// - integers and floats in decimal, bools as `true`/`false`/`1`/`0`
+/// - bytes in standard base64
+/// - geo as `lat,lon` (or `(lat, lon)`), in JSON also as a `[lat, lon]` array
+///
+/// Lines that can't be parsed are rejected with the reason and the others are still imported.
+#[derive(Debug, Clone)]
+pub struct Importer {
+    container: String,
+    format: Format,
+    schema: Option<Vec<(String, u8)>>,
+    hints: Vec<(String, u8)>,
+    fields: Vec<(String, String)>,
+    bulk: BulkInsert,
+}
+
+impl Importer {
+    pub fn new(container: &str, format: Format) -> Self {
+        Importer {
+            container: container.to_string(),
+            format,
+            schema: None,
+            hints: Vec::new(),
+            fields: Vec::new(),
+            bulk: BulkInsert::new(container, Vec::new()),
+        }
+    }
+    /// Declare the columns of the rows with their column type (e.g. `BIGINT`), in container order.
+    pub fn schema(mut self, columns: &[(&str, u8)]) -> Self {
+        self.schema = Some(columns.iter().map(|(c, t)| (c.to_string(), *t)).collect());
+        self
+    }
+    /// Parse the values of `column` as `column_type` when there is no declared schema.
+    pub fn hint(mut self, column: &str, column_type: u8) -> Self {
+        self.hints.push((column.to_string(), column_type));
+        self
+    }
+    /// Feed `column` from the source field `field`.
+    pub fn map_field(mut self, field: &str, column: &str) -> Self {
+        self.fields.push((field.to_string(), column.to_string()));
+        self
+    }
+    /// The chunking of the insertion, its container and columns are set by the importer.
+    pub fn bulk(mut self, bulk: BulkInsert) -> Self {
+        self.bulk = bulk;
+        self
+    }
+    /// Read the header of `reader` (the first object for JSON Lines) and return the iterator over its rows.
+    pub fn records<R: Read>(&self, reader: R) -> Result<Records<R>, Error> {
+        let mut pending = None;
+        let (source, fields) = match self.format {
+            Format::Csv => {
+                let mut reader = csv::ReaderBuilder::new().has_headers(true).from_reader(reader);
+                let header = reader
+                    .headers()
+                    .map_err(|e| Error::new(ErrorKind::InvalidInput, &format!("Invalid CSV header: {}", e)))?;
+                let fields = header.iter().map(|f| f.to_string()).collect::<Vec<_>>();
+                (Source::Csv(reader, csv::StringRecord::new()), fields)
+            }
+            Format::JsonLines => {
+                let mut lines = BufReader::new(reader).lines();
+                let mut line = 0;
+                let mut fields = Vec::new();
+                // the fields of the first object are the columns when there is no schema
+                for text in lines.by_ref() {
+                    line += 1;
+                    let text = text.map_err(|e| Error::new(ErrorKind::InvalidInput, &e.to_string()))?;
+                    if text.trim().is_empty() {
+                        continue;
+                    }
+                    let object = parse_object(&text);
+                    if let Ok(object) = &object {
+                        fields = object.keys().cloned().collect();
+                    }
+                    pending = Some((line, object));
+                    break;
+                }
+                (Source::JsonLines(lines, line), fields)
+            }
+        };
+        let columns = match &self.schema {
+            Some(schema) => schema
+                .iter()
+                .map(|(name, column_type)| {
+                    let field = self.field_of(name);
+                    let index = fields.iter().position(|f| *f == field);
+                    if self.format == Format::Csv && index.is_none() {
+                        return Err(Error::new(ErrorKind::InvalidInput, &format!("The CSV header has no field `{}` for column `{}`", field, name)));
+                    }
+                    Ok(Column { name: name.clone(), field, index: index.unwrap_or(0), column_type: Some(*column_type) })
+                })
+                .collect::<Result<Vec<_>, Error>>()?,
+            None => fields
+                .iter()
+                .enumerate()
+                .map(|(index, field)| {
+                    let name = self.column_of(field);
+                    let column_type = self.hints.iter().find(|(c, _)| *c == name).map(|(_, t)| *t);
+                    Column { name, field: field.clone(), index, column_type }
+                })
+                .collect(),
+        };
+        if columns.is_empty() {
+            return Err(Error::new(ErrorKind::InvalidInput, "The source has no fields to import"));
+        }
+        if let Some(Column { column_type: Some(t), name, .. }) = columns.iter().find(|c| c.column_type.is_some_and(|t| value_type_id(t).is_none())) {
+            return Err(Error::new(ErrorKind::InvalidInput, &format!("Column `{}` has the invalid column type {}", name, t)));
+        }
+        Ok(Records { source, columns, pending, rejected: Vec::new(), lines: Vec::new() })
+    }
+    /// The `BulkInsert` of the rows of `records`.
+    pub fn bulk_insert<R: Read>(&self, records: &Records<R>) -> BulkInsert {
+        let mut bulk = self.bulk.clone();
+        bulk.container = self.container.clone();
+        bulk.columns = records.columns();
+        bulk
+    }
+    fn field_of(&self, column: &str) -> String {
+        self.fields.iter().find(|(_, c)| c == column).map_or(column, |(f, _)| f).to_string()
+    }
+    fn column_of(&self, field: &str) -> String {
+        self.fields.iter().find(|(f, _)| f == field).map_or(field, |(_, c)| c).to_string()
+    }
+}
+
+type Object = Map<String, Value>;
+
+struct Column {
+    name: String,
+    field: String,
+    index: usize,
+    column_type: Option<u8>,
+}
+
+impl Column {
+    /// Keep the type of the first value parsed without a type as the type of the column, for its later values.
+    fn infer(&mut self, value: Result<AlbaTypes, String>) -> Result<AlbaTypes, String> {
+        let value = value.map_err(|e| format!("Field `{}`: {}", self.field, e))?;
+        if self.column_type.is_none() {
+            self.column_type = match value {
+                AlbaTypes::I64(_) => Some(BIGINT),
+                AlbaTypes::U64(_) => Some(UBIGINT),
+                AlbaTypes::F64(_) => Some(FLOAT),
+                AlbaTypes::Bool(_) => Some(BOOL),
+                AlbaTypes::Geo(_) => Some(GEO),
+                AlbaTypes::String(_) => Some(MEDIUM_STRING),
+                _ => None,
+            };
+        }
+        Ok(value)
+    }
+}
+
+enum Source<R: Read> {
+    Csv(csv::Reader<R>, csv::StringRecord),
+    JsonLines(Lines<BufReader<R>>, usize),
+}
+
+/// A line of the source that was not imported.
+#[derive(Debug, Clone)]
+pub struct Rejected {
+    /// The line number in the source, starting at 1 (the CSV header being line 1).
+    pub line: usize,
+    pub reason: String,
+}
+
+/// The outcome of an import.
+#[derive(Debug, Clone)]
+pub struct ImportReport {
+    /// Rows inserted.
+    pub imported: usize,
+    /// Lines that could not be parsed, or whose chunk the server refused, in line order.
+    pub rejected: Vec<Rejected>,
+    /// The outcome of the chunks of the insertion.
+    pub chunks: BulkReport,
+}
+
+/// The rows of an import source, see `Importer::records`. Rejected lines are skipped and kept for the report.
+pub struct Records<R: Read> {
+    source: Source<R>,
+    columns: Vec<Column>,
+    pending: Option<(usize, Result<Object, String>)>,
+    rejected: Vec<Rejected>,
+    lines: Vec<usize>,
+}
+
+impl<R: Read> Records<R> {
+    /// The container columns of the rows.
+    pub fn columns(&self) -> Vec<String> {
+        self.columns.iter().map(|c| c.name.clone()).collect()
+    }
+    pub fn rejected(&self) -> &[Rejected] {
+        &self.rejected
+    }
+    /// Build the report of the import from the report of the insertion of the rows.
+    pub fn finish(mut self, chunks: BulkReport) -> ImportReport {
+        for chunk in chunks.failed() {
+            let reason = match &chunk.result {
+                Err(e) => e.message().to_string(),
+                Ok(()) => continue,
+            };
+            for row in chunk.first_row..chunk.first_row + chunk.rows {
+                if let Some(line) = self.lines.get(row) {
+                    self.rejected.push(Rejected { line: *line, reason: reason.clone() });
+                }
+            }
+        }
+        self.rejected.sort_by_key(|r| r.line);
+        ImportReport { imported: chunks.inserted_rows(), rejected: self.rejected, chunks }
+    }
+    fn next_line(&mut self) -> Option<(usize, Result<Vec<AlbaTypes>, String>)> {
+        match &mut self.source {
+            Source::Csv(reader, record) => {
+                let result = reader.read_record(record);
+                let line = record.position().map_or(0, |p| p.line() as usize);
+                match result {
+                    Ok(false) => None,
+                    Ok(true) => Some((line, csv_row(&mut self.columns, record))),
+                    Err(e) => {
+                        let line = e.position().map_or(line, |p| p.line() as usize);
+                        Some((line, Err(e.to_string())))
+                    }
+                }
+            }
+            Source::JsonLines(lines, line) => {
+                let (number, object) = match self.pending.take() {
+                    Some(pending) => pending,
+                    None => loop {
+                        let text = lines.next()?;
+                        *line += 1;
+                        match text {
+                            Ok(text) if text.trim().is_empty() => continue,
+                            Ok(text) => break (*line, parse_object(&text)),
+                            Err(e) => break (*line, Err(e.to_string())),
+                        }
+                    },
+                };
+                Some((number, object.and_then(|object| json_row(&mut self.columns, &object))))
+            }
+        }
+    }
+}
+
+impl<R: Read> Iterator for Records<R> {
+    type Item = Vec<AlbaTypes>;
+    fn next(&mut self) -> Option<Vec<AlbaTypes>> {
+        loop {
+            let (line, row) = self.next_line()?;
+            match row {
+                Ok(row) => {
+                    self.lines.push(line);
+                    return Some(row);
+                }
+                Err(reason) => self.rejected.push(Rejected { line, reason }),
+            }
+        }
+    }
+}
+
+fn parse_object(text: &str) -> Result<Object, String> {
+    match serde_json::from_str::<Value>(text) {
+        Ok(Value::Object(object)) => Ok(object),
+        Ok(_) => Err("The line is not a JSON object".to_string()),
+        Err(e) => Err(format!("Invalid JSON: {}", e)),
+    }
+}
+
+fn csv_row(columns: &mut [Column], record: &csv::StringRecord) -> Result<Vec<AlbaTypes>, String> {
+    columns
+        .iter_mut()
+        .map(|column| match record.get(column.index) {
+            Some(text) => column.infer(parse_text(text, column.column_type)),
+            None => Err(format!("Missing field `{}`", column.field)),
+        })
+        .collect()
+}
+
+fn json_row(columns: &mut [Column], object: &Object) -> Result<Vec<AlbaTypes>, String> {
+    columns
+        .iter_mut()
+        .map(|column| match object.get(&column.field) {
+            Some(value) => column.infer(parse_json(value, column.column_type)),
+            None => Err(format!("Missing field `{}`", column.field)),
+        })
+        .collect()
+}
+
+/// Parse `value` as a value of `column_type`, or infer its type without one.
+fn parse_json(value: &Value, column_type: Option<u8>) -> Result<AlbaTypes, String> {
+    match (value, column_type) {
+        (Value::String(s), _) => parse_text(s, column_type),
+        (Value::Bool(b), None) => Ok(AlbaTypes::Bool(*b)),
+        (Value::Bool(b), Some(_)) => parse_text(if *b { "true" } else { "false" }, column_type),
+        (Value::Number(n), None) => Ok(match (n.as_i64(), n.as_u64(), n.as_f64()) {
+            (Some(v), _, _) => AlbaTypes::I64(v),
+            (_, Some(v), _) => AlbaTypes::U64(v),
+            (_, _, Some(v)) => AlbaTypes::F64(v),
+            _ => return Err(format!("Unsupported number {}", n)),
+        }),
+        (Value::Number(n), Some(_)) => parse_text(&n.to_string(), column_type),
+        (Value::Array(pair), _) if pair.len() == 2 && column_type.is_none_or(|t| value_type_id(t) == Some(13)) => {
+            match (pair[0].as_f64(), pair[1].as_f64()) {
+                (Some(lat), Some(lon)) => Ok(AlbaTypes::Geo((lat, lon))),
+                _ => Err("A geo value is an array of two numbers".to_string()),
+            }
+        }
+        (Value::Null, _) => Err("Null values can't be stored".to_string()),
+        _ => Err(format!("Unsupported value {}", value)),
+    }
+}
+
+/// Parse `text` as a value of `column_type`, or infer its type without one.
+fn parse_text(text: &str, column_type: Option<u8>) -> Result<AlbaTypes, String> {
+    let Some(column_type) = column_type else {
+        let trimmed = text.trim();
+        return Ok(if let Ok(v) = trimmed.parse::<i64>() {
+            AlbaTypes::I64(v)
+        } else if let Ok(v) = trimmed.parse::<f64>().map_err(|_| ()).and_then(|v| if v.is_finite() { Ok(v) } else { Err(()) }) {
+            AlbaTypes::F64(v)
+        } else if let Ok(v) = trimmed.parse::<bool>() {
+            AlbaTypes::Bool(v)
+        } else {
+            AlbaTypes::String(text.to_string())
+        });
+    };
+    let name = column_type_name(column_type).unwrap_or("?");
+    let invalid = || format!("`{}` is not a valid {} value", text, name);
+    let trimmed = text.trim();
+    macro_rules! number {
+        ($t:ty, $variant:ident) => {
+            AlbaTypes::$variant(trimmed.parse::<$t>().map_err(|_| invalid())?)
+        };
+    }
+    Ok(match (value_type_id(column_type), column_type) {
+        (Some(0), _) => AlbaTypes::String(text.to_string()),
+        (Some(11), _) => AlbaTypes::Bytes(STANDARD.decode(trimmed).map_err(|e| format!("Invalid base64 for {}: {}", name, e))?),
+        (Some(9), NANO_INT) => AlbaTypes::I32(trimmed.parse::<i8>().map_err(|_| invalid())?.into()),
+        (Some(9), SHORT) => AlbaTypes::I32(trimmed.parse::<i16>().map_err(|_| invalid())?.into()),
+        (Some(9), _) => number!(i32, I32),
+        (Some(10), _) => number!(i64, I64),
+        (Some(12), _) => number!(i128, I128),
+        (Some(1), _) => number!(u8, U8),
+        (Some(2), _) => number!(u16, U16),
+        (Some(3), _) => number!(u32, U32),
+        (Some(4), _) => number!(u64, U64),
+        (Some(5), _) => number!(u128, U128),
+        (Some(7), _) => number!(f64, F64),
+        (Some(8), _) => AlbaTypes::Bool(match trimmed.to_ascii_lowercase().as_str() {
+            "true" | "1" => true,
+            "false" | "0" => false,
+            _ => return Err(invalid()),
+        }),
+        (Some(13), _) => {
+            let inner = trimmed.strip_prefix('(').and_then(|t| t.strip_suffix(')')).unwrap_or(trimmed);
+            let (lat, lon) = inner.split_once(',').ok_or_else(invalid)?;
+            AlbaTypes::Geo((lat.trim().parse().map_err(|_| invalid())?, lon.trim().parse().map_err(|_| invalid())?))
+        }
+        _ => return Err(format!("Invalid column type {}", column_type)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn import(importer: &Importer, source: &str) -> (Vec<Vec<AlbaTypes>>, Vec<usize>) {
+        let mut records = importer.records(source.as_bytes()).unwrap();
+        let rows = records.by_ref().collect();
+        (rows, records.rejected().iter().map(|r| r.line).collect())
+    }
+
+    #[test]
+    fn columns_keep_the_type_of_their_first_value() {
+        let importer = Importer::new("c", Format::Csv);
+        let (rows, rejected) = import(&importer, "id,price,name\n1,2.5,a\n2,3,4\n3.5,1,true\nx,1,b\n");
+        assert_eq!(
+            rows,
+            vec![
+                vec![AlbaTypes::I64(1), AlbaTypes::F64(2.5), AlbaTypes::String("a".to_string())],
+                vec![AlbaTypes::I64(2), AlbaTypes::F64(3.0), AlbaTypes::String("4".to_string())],
+            ]
+        );
+        // `3.5` and `x` are not BIGINT values
+        assert_eq!(rejected, vec![4, 5]);
+    }
+
+    #[test]
+    fn hints_and_schemas_set_the_column_types() {
+        let importer = Importer::new("c", Format::Csv).hint("price", FLOAT).hint("code", MEDIUM_STRING);
+        let (rows, rejected) = import(&importer, "price,code\n1,007\n2.5,abc\n");
+        assert_eq!(rows, vec![vec![AlbaTypes::F64(1.0), AlbaTypes::String("007".to_string())], vec![AlbaTypes::F64(2.5), AlbaTypes::String("abc".to_string())]]);
+        assert!(rejected.is_empty());
+
+        let importer = Importer::new("c", Format::Csv).schema(&[("at", GEO), ("ok", BOOL), ("raw", crate::types::SMALL_BYTES)]);
+        let (rows, rejected) = import(&importer, "raw,ok,at\naGk=,1,\"(1.5, -2)\"\n!,0,0\n");
+        assert_eq!(rows, vec![vec![AlbaTypes::Geo((1.5, -2.0)), AlbaTypes::Bool(true), AlbaTypes::Bytes(b"hi".to_vec())]]);
+        assert_eq!(rejected, vec![3]);
+    }
+
+    #[test]
+    fn json_lines_infer_their_types_once() {
+        let importer = Importer::new("c", Format::JsonLines);
+        let source = "{\"n\": 1, \"at\": [1, 2], \"s\": \"a\"}\n\n{\"n\": \"2\", \"at\": \"3,4\", \"s\": 5}\n{\"n\": 1.5, \"at\": [0, 0], \"s\": \"b\"}\n{\"n\": null}\n";
+        let (rows, rejected) = import(&importer, source);
+        // the columns are the keys of the first object, in key order
+        assert_eq!(
+            rows,
+            vec![
+                vec![AlbaTypes::Geo((1.0, 2.0)), AlbaTypes::I64(1), AlbaTypes::String("a".to_string())],
+                vec![AlbaTypes::Geo((3.0, 4.0)), AlbaTypes::I64(2), AlbaTypes::String("5".to_string())],
+            ]
+        );
+        assert_eq!(rejected, vec![4, 5]);
+    }
+}
@@ -0,0 +1,609 @@
+use std::fmt;
+
+use crate::{
+    albastream::{Error, ErrorKind},
+    commands::Commands,
+    db_response::DBResponse,
+    logical_operators::LogicalOperator,
+    types::{AlbaTypes, column_type_name},
+};
+
+/// One annotated field of an encoded payload: `len` bytes starting at `offset`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub offset: usize,
+    pub len: usize,
+    /// Nesting level, the commands of a batch are one level deeper than the batch.
+    pub depth: usize,
+    pub description: String,
+}
+
+/// Why a payload couldn't be decoded, `offset` is where the offending field starts when it is known.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Failure {
+    pub offset: Option<usize>,
+    pub message: String,
+}
+
+/// What a successfully inspected payload decodes to.
+pub enum Decoded {
+    Command(Commands),
+    Response(DBResponse),
+    /// A reply whose status byte flags an error, with the message sent by the server.
+    ServerError(String),
+}
+
+/// The annotated byte-offset breakdown of a payload, see `inspect_command` and `inspect_response`.
+pub struct Inspection {
+    pub bytes: Vec<u8>,
+    pub fields: Vec<Field>,
+    pub decoded: Option<Decoded>,
+    pub failure: Option<Failure>,
+}
+
+const PREVIEW_BYTES: usize = 8;
+
+impl fmt::Display for Inspection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for field in self.fields.iter() {
+            let bytes = &self.bytes[field.offset..field.offset + field.len];
+            let mut hex = bytes.iter().take(PREVIEW_BYTES).map(|b| format!("{:02x}", b)).collect::<Vec<String>>().join(" ");
+            if bytes.len() > PREVIEW_BYTES {
+                hex.push_str(" ..");
+            }
+            writeln!(f, "offset {:>5}: {:<26} {}{}", field.offset, hex, "  ".repeat(field.depth), field.description)?;
+        }
+        match (&self.failure, &self.decoded) {
+            (Some(Failure { offset: Some(offset), message }), _) => write!(f, "error at offset {}: {}", offset, message),
+            (Some(Failure { offset: None, message }), _) => write!(f, "error: {}", message),
+            (None, Some(Decoded::Command(c))) => write!(f, "decoded: {}", c),
+            (None, Some(Decoded::Response(r))) => write!(f, "decoded:\n{}", r),
+            (None, Some(Decoded::ServerError(m))) => write!(f, "server error: {}", m),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+fn command_name(id: u8) -> Option<&'static str> {
+    Some(match id {
+        0 => "CreateContainer",
+        1 => "CreateRow",
+        2 => "EditRow",
+        3 => "DeleteRow",
+        4 => "DeleteContainer",
+        5 => "Search",
+        6 => "Commit",
+        7 => "Rollback",
+        8 => "BatchCreateRows",
+        9 => "Batch",
+        _ => return None,
+    })
+}
+
+fn type_name(id: u8) -> Option<&'static str> {
+    Some(match id {
+        0 => "String",
+        1 => "U8",
+        2 => "U16",
+        3 => "U32",
+        4 => "U64",
+        5 => "U128",
+        6 => "F32",
+        7 => "F64",
+        8 => "Bool",
+        9 => "I32",
+        10 => "I64",
+        11 => "Bytes",
+        12 => "I128",
+        13 => "Geo",
+        _ => return None,
+    })
+}
+
+/// Walks a payload with the same layout rules as the decoders, recording a `Field` per read.
+struct Cursor<'a> {
+    input: &'a [u8],
+    offset: usize,
+    end: usize,
+    depth: usize,
+    fields: Vec<Field>,
+}
+
+type Walk<T> = Result<T, Failure>;
+
+impl<'a> Cursor<'a> {
+    fn new(input: &'a [u8]) -> Self {
+        Cursor { input, offset: 0, end: input.len(), depth: 0, fields: Vec::new() }
+    }
+    fn fail<T>(&self, offset: usize, message: String) -> Walk<T> {
+        Err(Failure { offset: Some(offset), message })
+    }
+    fn take(&mut self, len: usize, what: &str) -> Walk<&'a [u8]> {
+        if len > self.end - self.offset {
+            return self.fail(
+                self.offset,
+                format!("{} needs {} bytes, only {} left", what, len, self.end - self.offset),
+            );
+        }
+        let bytes = &self.input[self.offset..self.offset + len];
+        self.offset += len;
+        Ok(bytes)
+    }
+    fn record(&mut self, offset: usize, description: String) {
+        self.fields.push(Field { offset, len: self.offset - offset, depth: self.depth, description });
+    }
+    fn u8(&mut self, what: &str) -> Walk<u8> {
+        let offset = self.offset;
+        let v = self.take(1, what)?[0];
+        self.record(offset, format!("{} {}", what, v));
+        Ok(v)
+    }
+    fn u32(&mut self, what: &str) -> Walk<u32> {
+        let offset = self.offset;
+        let v = u32::from_le_bytes(self.take(4, what)?.try_into().unwrap_or([0; 4]));
+        self.record(offset, format!("{} {} (u32 LE)", what, v));
+        Ok(v)
+    }
+    fn text(&mut self, len: usize, what: &str) -> Walk<()> {
+        let offset = self.offset;
+        let bytes = self.take(len, what)?;
+        self.record(offset, format!("{} {:?}", what, String::from_utf8_lossy(bytes)));
+        Ok(())
+    }
+    /// A `u8` length followed by that many bytes of text.
+    fn short_text(&mut self, what: &str) -> Walk<()> {
+        let len = self.u8(&format!("{} length", what))? as usize;
+        self.text(len, what)
+    }
+    fn dynamic_integer(&mut self, what: &str) -> Walk<usize> {
+        let offset = self.offset;
+        let flag = self.take(1, "DynamicInteger flag")?[0];
+        let (variant, width) = match flag {
+            0 => ("U8", 1),
+            1 => ("U16", 2),
+            2 => ("U32", 4),
+            3 => ("U64", 8),
+            _ => return self.fail(offset, format!("invalid DynamicInteger flag {}, expected 0 to 3", flag)),
+        };
+        let mut le = [0u8; 8];
+        le[..width].copy_from_slice(self.take(width, &format!("DynamicInteger {}", variant))?);
+        let v = u64::from_le_bytes(le) as usize;
+        self.record(offset, format!("{} {} (DynamicInteger {}, {} bytes)", what, v, variant, width + 1));
+        Ok(v)
+    }
+    fn value(&mut self, what: &str) -> Walk<()> {
+        let offset = self.offset;
+        let id = self.take(1, "AlbaTypes id")?[0];
+        let name = match type_name(id) {
+            Some(n) => n,
+            None => return self.fail(offset, format!("invalid AlbaTypes id {}, expected 0 to 13", id)),
+        };
+        self.record(offset, format!("{}: AlbaTypes id {} ({})", what, id, name));
+        self.depth += 1;
+        let data = self.offset;
+        match id {
+            0 | 11 => {
+                let len = self.dynamic_integer("length")?;
+                let data = self.offset;
+                let bytes = self.take(len, &format!("{} data", name))?;
+                let v = if id == 0 { AlbaTypes::String(String::from_utf8_lossy(bytes).to_string()) } else { AlbaTypes::Bytes(bytes.to_vec()) };
+                self.record(data, format!("value {}", preview(&v)));
+            }
+            _ => {
+                let width = match id {
+                    1 | 8 => 1,
+                    2 => 2,
+                    3 | 6 | 9 => 4,
+                    4 | 7 | 10 => 8,
+                    _ => 16,
+                };
+                self.take(width, name)?;
+                let v = match AlbaTypes::from_bytes(&self.input[offset..self.offset]) {
+                    Ok((v, _)) => v,
+                    Err(e) => return self.fail(offset, e.to_string()),
+                };
+                self.record(data, format!("value {}", preview(&v)));
+            }
+        }
+        self.depth -= 1;
+        Ok(())
+    }
+    fn conditions(&mut self, gates_counted: bool) -> Walk<()> {
+        let count = self.u8("condition count")?;
+        for i in 0..count {
+            self.short_text(&format!("condition {} column", i))?;
+            let offset = self.offset;
+            let id = self.take(1, "operator id")?[0];
+            match LogicalOperator::from_id(id) {
+                Ok(op) => self.record(offset, format!("condition {} operator {} ({:?}, `{}`)", i, id, op, op)),
+                Err(_) => return self.fail(offset, format!("invalid operator id {}", id)),
+            }
+            self.value(&format!("condition {} value", i))?;
+        }
+        // searches carry one gate per condition without a count, edits and deletes prefix theirs with a count
+        let gates = if gates_counted { self.u8("logic gate count")? as usize } else { count as usize };
+        for i in 0..gates {
+            if !gates_counted && self.offset >= self.end {
+                break;
+            }
+            let offset = self.offset;
+            let bytes = self.take(2, "logic gate")?;
+            self.record(offset, format!("logic gate {}: index {}, {}", i, bytes[0], if bytes[1] == 0 { "OR" } else { "AND" }));
+        }
+        Ok(())
+    }
+    fn columns(&mut self) -> Walk<usize> {
+        let count = self.u8("column count")?;
+        for i in 0..count {
+            self.short_text(&format!("column {}", i))?;
+        }
+        Ok(count as usize)
+    }
+
+    fn command(&mut self) -> Walk<()> {
+        let offset = self.offset;
+        let id = self.take(1, "op id")?[0];
+        let name = match command_name(id) {
+            Some(n) => n,
+            None => return self.fail(offset, format!("invalid op id {}, expected 0 to 9", id)),
+        };
+        self.record(offset, format!("op id {} ({})", id, name));
+        match id {
+            0 => {
+                self.short_text("container name")?;
+                self.columns()?;
+                while self.offset < self.end {
+                    let offset = self.offset;
+                    let t = self.take(1, "column type")?[0];
+                    self.record(offset, format!("column type {} ({})", t, column_type_name(t).unwrap_or("unknown")));
+                }
+            }
+            1 => {
+                self.short_text("container name")?;
+                let count = self.columns()?;
+                for i in 0..count {
+                    self.value(&format!("column {} value", i))?;
+                }
+            }
+            2 => {
+                self.short_text("container name")?;
+                let count = self.columns()?;
+                for i in 0..count {
+                    self.value(&format!("column {} value", i))?;
+                }
+                self.conditions(true)?;
+            }
+            3 => {
+                self.short_text("container name")?;
+                if self.u8("has conditions")? != 0 {
+                    self.conditions(true)?;
+                }
+            }
+            4 => {
+                let len = self.end - self.offset;
+                self.text(len, "container name")?;
+            }
+            5 => {
+                self.columns()?;
+                self.conditions(false)?;
+                let offset = self.offset;
+                let len = u64::from_le_bytes(self.take(8, "container length")?.try_into().unwrap_or([0; 8])) as usize;
+                self.record(offset, format!("container length {} (u64 LE)", len));
+                let limit = self.offset.saturating_add(len);
+                if limit > self.end {
+                    return self.fail(offset, format!("the container needs {} bytes, only {} left", len, self.end - self.offset));
+                }
+                let end = std::mem::replace(&mut self.end, limit);
+                self.short_text("container name")?;
+                self.end = end;
+            }
+            6 | 7 => {
+                if self.u8("has container")? != 0 {
+                    self.short_text("container name")?;
+                }
+            }
+            8 => {
+                self.short_text("container name")?;
+                let count = self.columns()?;
+                let offset = self.offset;
+                let rows = self.u32("row count")?;
+                // untrusted counts are checked against the bytes left before walking, a value takes at least 2 bytes
+                if count == 0 && rows > 0 {
+                    return self.fail(offset, format!("{} rows without columns", rows));
+                }
+                let needed = rows as usize * count * 2;
+                if needed > self.end - self.offset {
+                    return self.fail(offset, format!("{} rows of {} values need at least {} bytes, only {} left", rows, count, needed, self.end - self.offset));
+                }
+                for r in 0..rows {
+                    for i in 0..count {
+                        self.value(&format!("row {} column {} value", r, i))?;
+                    }
+                }
+            }
+            _ => {
+                let offset = self.offset;
+                let count = i32::from_le_bytes(self.take(4, "command count")?.try_into().unwrap_or([0; 4]));
+                self.record(
+                    offset,
+                    format!("command count {} (i32 LE, {})", count.unsigned_abs(), if count < 0 { "transaction" } else { "not a transaction" }),
+                );
+                // every command is prefixed by its u32 length
+                if count.unsigned_abs() as usize > (self.end - self.offset) / 4 {
+                    return self.fail(offset, format!("{} commands need at least {} bytes, only {} left", count.unsigned_abs(), count.unsigned_abs() as usize * 4, self.end - self.offset));
+                }
+                for i in 0..count.unsigned_abs() {
+                    let len = self.u32(&format!("command {} length", i))? as usize;
+                    if self.offset + len > self.end {
+                        return self.fail(self.offset, format!("command {} needs {} bytes, only {} left", i, len, self.end - self.offset));
+                    }
+                    let end = std::mem::replace(&mut self.end, self.offset + len);
+                    self.depth += 1;
+                    self.command()?;
+                    self.trailing("command")?;
+                    self.depth -= 1;
+                    self.end = end;
+                }
+            }
+        }
+        Ok(())
+    }
+    /// Records the bytes left before `end`, which the decoders ignore.
+    fn trailing(&mut self, what: &str) -> Walk<()> {
+        if self.offset < self.end {
+            let offset = self.offset;
+            let len = self.end - offset;
+            self.take(len, "trailing bytes")?;
+            self.record(offset, format!("{} trailing bytes ignored by the {} decoder", len, what));
+        }
+        Ok(())
+    }
+    fn rows(&mut self) -> Walk<()> {
+        let mut row = 0;
+        while self.offset < self.end {
+            let offset = self.offset;
+            let count = self.dynamic_integer(&format!("row {} value count", row))?;
+            if count > (self.end - self.offset) / 2 {
+                return self.fail(offset, format!("{} values need at least {} bytes, only {} left", count, count.saturating_mul(2), self.end - self.offset));
+            }
+            self.depth += 1;
+            for i in 0..count {
+                self.value(&format!("row {} column {}", row, i))?;
+            }
+            self.depth -= 1;
+            row += 1;
+        }
+        Ok(())
+    }
+}
+
+fn preview(value: &AlbaTypes) -> String {
+    let text = match value {
+        AlbaTypes::String(s) => format!("{:?}", s),
+        v => v.to_string(),
+    };
+    if text.chars().count() > 64 { format!("{}.. ({} chars)", text.chars().take(64).collect::<String>(), text.chars().count()) } else { text }
+}
+
+fn finish(input: &[u8], cursor: Cursor<'_>, walk: Walk<()>, decode: impl FnOnce() -> Result<Decoded, Error>) -> Inspection {
+    let (decoded, failure) = match walk {
+        // the decoders index without bounds checks on some paths, they only run over a payload the walk accepted
+        Ok(()) => match decode() {
+            Ok(d) => (Some(d), None),
+            Err(e) => (None, Some(Failure { offset: None, message: format!("the decoder rejected the payload: {}", e) })),
+        },
+        Err(f) => (None, Some(f)),
+    };
+    Inspection { bytes: input.to_vec(), fields: cursor.fields, decoded, failure }
+}
+
+/// Annotated breakdown of a compiled command (a `CompiledAlba`), decoded with `Commands::decompile`.
+pub fn inspect_command(input: &[u8]) -> Inspection {
+    let mut cursor = Cursor::new(input);
+    let walk = if input.len() < 2 {
+        cursor.fail(0, format!("a compiled command has at least 2 bytes, got {}", input.len()))
+    } else {
+        cursor.command().and_then(|_| cursor.trailing("command"))
+    };
+    finish(input, cursor, walk, || Commands::decompile(input).map(Decoded::Command))
+}
+
+/// Annotated breakdown of the rows of a response payload, decoded with `DBResponse::decode`.
+pub fn inspect_response(input: &[u8]) -> Inspection {
+    let mut cursor = Cursor::new(input);
+    let walk = cursor.rows();
+    finish(input, cursor, walk, || DBResponse::decode(input).map(|r| Decoded::Response(r.0)))
+}
+
+/// Annotated breakdown of a raw server reply: the status byte then the rows, or the error message.
+pub fn inspect_reply(input: &[u8]) -> Inspection {
+    let mut cursor = Cursor::new(input);
+    let status = match input.first() {
+        Some(s) => *s,
+        None => {
+            let walk = cursor.fail(0, "the reply is empty".to_string());
+            return finish(input, cursor, walk, || Err(Error::new(ErrorKind::UnexpectedEof, "Empty response")));
+        }
+    };
+    cursor.offset = 1;
+    cursor.record(0, format!("status {} ({})", status, if status == 1 { "error" } else { "ok" }));
+    if status == 1 {
+        let len = input.len() - 1;
+        let walk = cursor.text(len, "error message");
+        return finish(input, cursor, walk, || Ok(Decoded::ServerError(String::from_utf8_lossy(&input[1..]).to_string())));
+    }
+    let walk = cursor.rows();
+    finish(input, cursor, walk, || DBResponse::decode(&input[1..]).map(|r| Decoded::Response(r.0)))
+}
+
+/// Reads a byte dump: hex (`05 02 00`, `0502`, `0x05, 0x02`) or a Rust `{:?}` decimal list (`[5, 2, 0]`).
+pub fn parse_dump(text: &str) -> Result<Vec<u8>, Error> {
+    let text = text.trim();
+    if let Some(list) = text.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+        return list
+            .split(',')
+            .map(|n| n.trim())
+            .filter(|n| !n.is_empty())
+            .map(|n| n.parse::<u8>().map_err(|_| Error::new(ErrorKind::InvalidInput, &format!("Invalid byte {:?} in the list", n))))
+            .collect();
+    }
+    let mut digits = String::with_capacity(text.len());
+    for token in text.split(|c: char| c.is_whitespace() || c == ',' || c == ':') {
+        let token = token.strip_prefix("0x").or_else(|| token.strip_prefix("0X")).unwrap_or(token);
+        if !token.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(Error::new(ErrorKind::InvalidInput, &format!("Invalid hex {:?}", token)));
+        }
+        // a lone `0x5` style token is a single byte
+        if token.len() == 1 {
+            digits.push('0');
+        }
+        digits.push_str(token);
+    }
+    if !digits.len().is_multiple_of(2) {
+        return Err(Error::new(ErrorKind::InvalidInput, "The hex dump has an odd number of digits"));
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).map_err(|_| Error::new(ErrorKind::InvalidInput, "Invalid hex digit")))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db_response::Row,
+        handler::{BatchBuilder, BatchCreateRowsBuilder, CreateContainerBuilder, DeleteRowBuilder, EditRowBuilder, SearchBuilder},
+        types::{BIGINT, GEO, MEDIUM_STRING},
+    };
+
+    /// xorshift64*, deterministic so that a failure can be replayed
+    struct Rng(u64);
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 >> 12;
+            self.0 ^= self.0 << 25;
+            self.0 ^= self.0 >> 27;
+            self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+        }
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+    }
+
+    fn commands() -> Vec<Vec<u8>> {
+        let search = SearchBuilder::new()
+            .add_container("nice_container".into())
+            .add_column_name("id".into())
+            .add_conditions(("id".into(), LogicalOperator::HigherEquality, AlbaTypes::I64(5)), true)
+            .add_conditions(("s".into(), LogicalOperator::StringContains, AlbaTypes::String("legal".into())), true);
+        let edit = EditRowBuilder::new()
+            .put_container("nice_container".into())
+            .edit_column("s".into(), AlbaTypes::String("new".into()))
+            .add_conditions(("p".into(), LogicalOperator::Equal, AlbaTypes::Geo((1.5, 2.5))), true);
+        let delete = DeleteRowBuilder::new().put_container("nice_container".into()).add_conditions(("b".into(), LogicalOperator::Diferent, AlbaTypes::Bytes(vec![0, 255])), true);
+        let create = CreateContainerBuilder { container: "nice_container".into(), headers: (vec!["id".into(), "s".into(), "p".into()], vec![BIGINT, MEDIUM_STRING, GEO]) };
+        let batch = BatchBuilder::new().transaction(true).push(search.clone()).push(delete.clone());
+        let rows = BatchCreateRowsBuilder::new()
+            .put_container("nice_container".into())
+            .set_columns(vec!["id".into(), "s".into()])
+            .insert_value(vec![AlbaTypes::I64(1), AlbaTypes::String("a".into())])
+            .insert_value(vec![AlbaTypes::I64(2), AlbaTypes::String("b".into())]);
+        vec![
+            search.cloned_finish().unwrap(),
+            edit.cloned_finish().unwrap(),
+            delete.cloned_finish().unwrap(),
+            create.finish().unwrap(),
+            batch.finish().unwrap(),
+            rows.finish().unwrap(),
+        ]
+    }
+
+    fn response() -> Vec<u8> {
+        DBResponse::new(vec![
+            Row::new(vec![AlbaTypes::I64(-1), AlbaTypes::String("a".into()), AlbaTypes::Bytes(vec![1, 2, 3])]),
+            Row::new(vec![AlbaTypes::U128(u128::MAX), AlbaTypes::Geo((1.0, -1.0)), AlbaTypes::Bool(true), AlbaTypes::F32(0.5)]),
+        ])
+        .encode()
+    }
+
+    fn inspect_all(input: &[u8]) {
+        let _ = inspect_command(input).to_string();
+        let _ = inspect_response(input).to_string();
+        let _ = inspect_reply(input).to_string();
+    }
+
+    #[test]
+    fn valid_payloads_decode() {
+        for command in commands() {
+            let inspection = inspect_command(&command);
+            assert!(inspection.failure.is_none(), "{}", inspection);
+        }
+        assert!(inspect_response(&response()).failure.is_none());
+        let mut reply = vec![0u8];
+        reply.extend(response());
+        assert!(inspect_reply(&reply).failure.is_none());
+    }
+
+    #[test]
+    fn truncated_payloads_never_panic() {
+        let mut reply = vec![0u8];
+        reply.extend(response());
+        for payload in commands().into_iter().chain([response(), reply]) {
+            for len in 0..payload.len() {
+                inspect_all(&payload[..len]);
+            }
+        }
+    }
+
+    #[test]
+    fn corrupted_payloads_never_panic() {
+        let mut rng = Rng(0x9E37_79B9_7F4A_7C15);
+        let payloads: Vec<Vec<u8>> = commands().into_iter().chain([response()]).collect();
+        for _ in 0..20_000 {
+            let mut payload = payloads[rng.below(payloads.len())].clone();
+            for _ in 0..1 + rng.below(4) {
+                let i = rng.below(payload.len());
+                payload[i] = match rng.below(3) {
+                    0 => rng.next() as u8,
+                    1 => 0xff,
+                    _ => payload[i].wrapping_add(1),
+                };
+            }
+            payload.truncate(1 + rng.below(payload.len()));
+            inspect_all(&payload);
+        }
+    }
+
+    #[test]
+    fn oversized_counts_are_rejected() {
+        let payloads: [&[u8]; 4] = [
+            // BatchCreateRows without columns and u32::MAX rows
+            &[8, 0, 0, 0xff, 0xff, 0xff, 0xff],
+            &[8, 0, 1, 1, b'a', 0xff, 0xff, 0xff, 0xff, 1, 1],
+            // a batch of i32::MAX commands
+            &[9, 0xff, 0xff, 0xff, 0x7f, 0, 0, 0, 0],
+            // a row of u64::MAX values
+            &[3, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 1, 1],
+        ];
+        for payload in payloads {
+            inspect_all(payload);
+        }
+        for payload in &payloads[..3] {
+            assert!(inspect_command(payload).failure.is_some());
+            assert!(Commands::decompile(payload).is_err());
+        }
+        assert!(inspect_response(payloads[3]).failure.is_some());
+    }
+
+    #[test]
+    fn garbage_never_panics() {
+        let mut rng = Rng(0xD1B5_4A32_D192_ED03);
+        for _ in 0..20_000 {
+            let payload: Vec<u8> = (0..rng.below(96)).map(|_| rng.next() as u8).collect();
+            inspect_all(&payload);
+            let text: String = payload.iter().map(|b| *b as char).collect();
+            let _ = parse_dump(&text);
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use std::{io::Error as IoError, time::Duration};
+
+use tracing::{field, Span};
+
+use crate::{albastream::Error, commands::Commands, db_response::DBResponse};
+
+// The spans and events of the `tracing` feature, shared by the clients.
+//
+// `tytodb.connect`, `tytodb.execute` and `tytodb.heartbeat` spans at info level, their failures as error events
+// (warn for a heartbeat), retries and reconnections as events within `tytodb.execute`. The values of the
+// commands are only recorded when the client opted in with `with_traced_values`.
+
+fn millis(elapsed : Duration) -> f64{
+    elapsed.as_secs_f64() * 1000.0
+}
+
+pub(crate) fn connect_span(host : &str) -> Span{
+    tracing::info_span!("tytodb.connect", host, latency_ms = field::Empty)
+}
+
+pub(crate) fn record_connect<T>(span : &Span, result : &Result<T,IoError>, elapsed : Duration){
+    span.record("latency_ms", millis(elapsed));
+    if let Err(e) = result{
+        tracing::error!(parent: span, error = %e, "connection failed");
+    }
+}
+
+/// The span of a request, `values` recording the whole decoded command.
+pub(crate) fn execute_span(compiled_command : &[u8], values : bool) -> Span{
+    let span = tracing::info_span!(
+        "tytodb.execute",
+        command = Commands::kind_name(compiled_command.first().copied().unwrap_or(u8::MAX)),
+        container = field::Empty,
+        payload_bytes = compiled_command.len(),
+        reply_bytes = field::Empty,
+        rows = field::Empty,
+        latency_ms = field::Empty,
+        values = field::Empty,
+    );
+    if span.is_disabled(){
+        return span
+    }
+    match compiled_command.first(){
+        // the container leads a `BatchCreateRows`, decoding its rows would only cost
+        Some(8) => if let Some(container) = Commands::container_of(compiled_command){
+            span.record("container", container.as_str());
+        },
+        _ => if let Ok(command) = Commands::decompile(compiled_command){
+            if let Some(container) = command.container(){
+                span.record("container", container);
+            }
+            if values{
+                span.record("values", field::debug(&command));
+            }
+        },
+    }
+    span
+}
+
+pub(crate) fn record_reply(span : &Span, result : &Result<Vec<u8>,Error>, elapsed : Duration){
+    span.record("latency_ms", millis(elapsed));
+    match result{
+        Ok(payload) => {
+            span.record("reply_bytes", payload.len());
+            if !span.is_disabled(){
+                span.record("rows", DBResponse::iter_rows(payload).take_while(Result::is_ok).count());
+            }
+        },
+        Err(e) => tracing::error!(parent: span, kind = ?e.kind(), error = e.message(), "request failed"),
+    }
+}
+
+pub(crate) fn retrying(error : &Error, attempt : u32, delay : Duration){
+    tracing::warn!(attempt, delay_ms = millis(delay), kind = ?error.kind(), error = error.message(), "retrying request");
+}
+
+pub(crate) fn reconnected<T>(host : &str, result : &Result<T,IoError>){
+    match result{
+        Ok(_) => tracing::info!(host, "reconnected"),
+        Err(e) => tracing::warn!(host, error = %e, "reconnection failed"),
+    }
+}
+
+pub(crate) fn transaction_dropped(container : Option<&str>){
+    tracing::warn!(container, "transaction dropped without commit or rollback, rolling it back");
+}
+
+pub(crate) fn heartbeat_span(host : &str) -> Span{
+    tracing::info_span!("tytodb.heartbeat", host, latency_ms = field::Empty)
+}
+
+pub(crate) fn record_heartbeat(span : &Span, result : &Result<(),IoError>, elapsed : Duration){
+    span.record("latency_ms", millis(elapsed));
+    if let Err(e) = result{
+        tracing::warn!(parent: span, error = %e, "heartbeat failed");
+    }
+}
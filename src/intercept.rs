@@ -0,0 +1,103 @@
+#[cfg(any(feature="thread", feature="tokio", feature="asyncstd"))]
+use std::sync::Arc;
+
+#[cfg(any(feature="thread", feature="tokio", feature="asyncstd"))]
+use crate::albastream::CompiledAlba;
+use crate::{albastream::Error, commands::Commands, db_response::DBResponse};
+
+/// What a request becomes after `Interceptor::before`.
+pub enum Intercept{
+    /// Send the command as it is.
+    Continue,
+    /// Send this command instead, the next interceptors seeing it.
+    Rewrite(Commands),
+    /// Don't send the command, replying this instead (e.g. an error when a rate limit is reached).
+    Reply(Result<DBResponse,Error>),
+}
+
+/// Runs around the requests of a client, see `Client::with_interceptor`.
+///
+/// `before` is called in the order the interceptors were registered, `after` in the reverse order and only on the
+/// interceptors whose `before` ran, so a short-circuited request is seen by the `after` of the interceptors that
+/// let it through. Retries happen between the two, the interceptors see a request once. The requests of a
+/// `BulkInsert` are intercepted chunk by chunk.
+pub trait Interceptor : Send + Sync{
+    fn before(&self, _command : &Commands) -> Intercept{
+        Intercept::Continue
+    }
+    fn after(&self, _command : &Commands, _result : &Result<DBResponse,Error>){}
+}
+
+impl<F : Fn(&Commands) -> Intercept + Send + Sync> Interceptor for F{
+    fn before(&self, command : &Commands) -> Intercept{
+        self(command)
+    }
+}
+
+/// The interceptors of a client.
+#[cfg(any(feature="thread", feature="tokio", feature="asyncstd"))]
+#[derive(Clone, Default)]
+pub(crate) struct Interceptors(Vec<Arc<dyn Interceptor>>);
+
+/// A request after the `before` of the interceptors.
+#[cfg(any(feature="thread", feature="tokio", feature="asyncstd"))]
+pub(crate) enum Outgoing{
+    /// Send the compiled command, then pass the reply to `Interceptors::after` with the command.
+    Send(CompiledAlba, Option<Commands>),
+    /// The reply of a short-circuited request, the `after` of the interceptors already called.
+    Reply(Result<Vec<u8>,Error>),
+}
+
+#[cfg(any(feature="thread", feature="tokio", feature="asyncstd"))]
+impl Interceptors{
+    pub(crate) fn push(&mut self, interceptor : Arc<dyn Interceptor>){
+        self.0.push(interceptor);
+    }
+    pub(crate) fn before(&self, compiled_command : CompiledAlba) -> Outgoing{
+        if self.0.is_empty(){
+            return Outgoing::Send(compiled_command, None)
+        }
+        let mut command = match Commands::decompile(&compiled_command){
+            Ok(command) => command,
+            Err(e) => return Outgoing::Reply(Err(e))
+        };
+        let mut rewritten = false;
+        for (ran, interceptor) in self.0.iter().enumerate(){
+            match interceptor.before(&command){
+                Intercept::Continue => {},
+                Intercept::Rewrite(new) => {
+                    command = new;
+                    rewritten = true;
+                },
+                Intercept::Reply(reply) => {
+                    for interceptor in self.0[..=ran].iter().rev(){
+                        interceptor.after(&command, &reply);
+                    }
+                    return Outgoing::Reply(reply.map(|response| response.encode()))
+                }
+            }
+        }
+        if !rewritten{
+            return Outgoing::Send(compiled_command, Some(command))
+        }
+        match command.compile(){
+            Ok(compiled) => Outgoing::Send(compiled, Some(command)),
+            Err(e) => Outgoing::Reply(Err(e)),
+        }
+    }
+    /// Pass the reply of `command` to the `after` of the interceptors, the reply is decoded once for all of them.
+    pub(crate) fn after(&self, command : Option<Commands>, result : Result<Vec<u8>,Error>) -> Result<Vec<u8>,Error>{
+        let Some(command) = command else { return result };
+        let (decoded, result) = match result{
+            Ok(payload) => match DBResponse::from_bytes(&payload){
+                Ok(response) => (Ok(response), Ok(payload)),
+                Err(e) => (Err(e.clone()), Err(e)),
+            },
+            Err(e) => (Err(e.clone()), Err(e)),
+        };
+        for interceptor in self.0.iter().rev(){
+            interceptor.after(&command, &decoded);
+        }
+        result
+    }
+}
@@ -5,10 +5,33 @@ pub mod albastream;
 pub mod handler;
 pub mod dynamic_int;
 pub mod db_response;
+pub mod geo;
+pub mod aggregate;
+pub mod parser;
+pub mod inspect;
+pub mod retry;
+pub mod bulk;
+pub mod export;
+pub mod dump;
+pub mod migrate;
+pub mod metrics;
+pub mod intercept;
+#[cfg(any(feature="thread", feature="tokio", feature="asyncstd"))]
+pub mod cache;
+#[cfg(any(feature="thread", feature="tokio", feature="asyncstd"))]
+pub mod breaker;
+pub mod endpoints;
+pub mod secret;
+#[cfg(feature="import")]
+pub mod import;
+#[cfg(all(feature="tracing", any(feature="thread", feature="tokio", feature="asyncstd")))]
+mod instrument;
 pub use types::*;
 #[cfg(feature="thread")]
 pub mod client_thread;
 #[cfg(feature="tokio")]
 pub mod client_tokio;
 #[cfg(feature="asyncstd")]
-pub mod client_asyncstd;
\ No newline at end of file
+pub mod client_asyncstd;
+#[cfg(feature="blocking")]
+pub mod blocking;
\ No newline at end of file
@@ -1,5 +1,11 @@
-use crate::albastream::{Error, ErrorKind};
+use std::fmt;
 
+use crate::{albastream::{Error, ErrorKind}, commands::Condition, types::AlbaTypes};
+
+/// Operators of a condition.
+///
+/// TytoDB only understands the operators up to `StringRegex`, the others are lowered by the builders into native
+/// ones (see `lower`) before being sent.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum LogicalOperator{
     Equal,
@@ -10,7 +16,15 @@ pub enum LogicalOperator{
     LowerEquality,
     StringContains,
     StringContainsInsensitive,
-    StringRegex
+    StringRegex,
+    In,
+    NotIn,
+    Between,
+    StartsWith,
+    EndsWith,
+    NotStringContains,
+    NotStringContainsInsensitive,
+    NotStringRegex
 }
 impl LogicalOperator{
     pub fn id(&self) -> u8{
@@ -24,6 +38,14 @@ impl LogicalOperator{
             LogicalOperator::StringContains => 6,
             LogicalOperator::StringContainsInsensitive => 7,
             LogicalOperator::StringRegex => 8,
+            LogicalOperator::In => 9,
+            LogicalOperator::NotIn => 10,
+            LogicalOperator::Between => 11,
+            LogicalOperator::StartsWith => 12,
+            LogicalOperator::EndsWith => 13,
+            LogicalOperator::NotStringContains => 14,
+            LogicalOperator::NotStringContainsInsensitive => 15,
+            LogicalOperator::NotStringRegex => 16,
         }
     }
     
@@ -38,9 +60,189 @@ impl LogicalOperator{
             6 => Ok(LogicalOperator::StringContains),
             7 => Ok(LogicalOperator::StringContainsInsensitive),
             8 => Ok(LogicalOperator::StringRegex),
+            9 => Ok(LogicalOperator::In),
+            10 => Ok(LogicalOperator::NotIn),
+            11 => Ok(LogicalOperator::Between),
+            12 => Ok(LogicalOperator::StartsWith),
+            13 => Ok(LogicalOperator::EndsWith),
+            14 => Ok(LogicalOperator::NotStringContains),
+            15 => Ok(LogicalOperator::NotStringContainsInsensitive),
+            16 => Ok(LogicalOperator::NotStringRegex),
             _ => Err(Error::new(ErrorKind::InvalidInput, "Invalid LogicalOperator ID"))
         }
     }
+
+    /// The canonical spelling of the operator, as accepted by the `lo!` macro.
+    pub fn spelling(&self) -> &'static str{
+        match self{
+            LogicalOperator::Equal => "=",
+            LogicalOperator::Diferent => "!=",
+            LogicalOperator::Higher => ">",
+            LogicalOperator::Lower => "<",
+            LogicalOperator::HigherEquality => ">=",
+            LogicalOperator::LowerEquality => "<=",
+            LogicalOperator::StringContains => "&>",
+            LogicalOperator::StringContainsInsensitive => "&&>",
+            LogicalOperator::StringRegex => "&&&>",
+            LogicalOperator::In => "in",
+            LogicalOperator::NotIn => "not_in",
+            LogicalOperator::Between => "between",
+            LogicalOperator::StartsWith => "^>",
+            LogicalOperator::EndsWith => "$>",
+            LogicalOperator::NotStringContains => "!&>",
+            LogicalOperator::NotStringContainsInsensitive => "!&&>",
+            LogicalOperator::NotStringRegex => "!&&&>",
+        }
+    }
+
+    /// Parse any of the spellings accepted by the `lo!` macro (symbols or words).
+    pub fn from_spelling(spelling : &str) -> Option<LogicalOperator>{
+        Some(match spelling{
+            "=" | "eq" => LogicalOperator::Equal,
+            "!=" | "ne" => LogicalOperator::Diferent,
+            ">" | "gt" => LogicalOperator::Higher,
+            "<" | "lt" => LogicalOperator::Lower,
+            ">=" | "gte" => LogicalOperator::HigherEquality,
+            "<=" | "lte" => LogicalOperator::LowerEquality,
+            "&>" | "contains" => LogicalOperator::StringContains,
+            "&&>" | "icontains" => LogicalOperator::StringContainsInsensitive,
+            "&&&>" | "regex" => LogicalOperator::StringRegex,
+            "in" => LogicalOperator::In,
+            "!in" | "not_in" => LogicalOperator::NotIn,
+            "between" => LogicalOperator::Between,
+            "^>" | "starts_with" => LogicalOperator::StartsWith,
+            "$>" | "ends_with" => LogicalOperator::EndsWith,
+            "!&>" | "not_contains" => LogicalOperator::NotStringContains,
+            "!&&>" | "not_icontains" => LogicalOperator::NotStringContainsInsensitive,
+            "!&&&>" | "not_regex" => LogicalOperator::NotStringRegex,
+            _ => return None
+        })
+    }
+
+    /// Whether TytoDB understands the operator natively.
+    pub fn is_native(&self) -> bool{
+        self.id() <= 8
+    }
+
+    /// Evaluate a string operator on the client, `value` being the column value and `operand` the condition value.
+    ///
+    /// Returns `None` when the operator can't be evaluated on the client.
+    pub fn matches(&self, value : &AlbaTypes, operand : &AlbaTypes) -> Option<bool>{
+        let (value, operand) = match (value, operand){
+            (AlbaTypes::String(v), AlbaTypes::String(o)) => (v, o),
+            _ => return None
+        };
+        match self{
+            LogicalOperator::StringContains => Some(value.contains(operand.as_str())),
+            LogicalOperator::StringContainsInsensitive => Some(value.to_lowercase().contains(&operand.to_lowercase())),
+            LogicalOperator::NotStringContains => Some(!value.contains(operand.as_str())),
+            LogicalOperator::NotStringContainsInsensitive => Some(!value.to_lowercase().contains(&operand.to_lowercase())),
+            LogicalOperator::StartsWith => Some(value.starts_with(operand.as_str())),
+            LogicalOperator::EndsWith => Some(value.ends_with(operand.as_str())),
+            #[cfg(feature="regex")]
+            LogicalOperator::StringRegex | LogicalOperator::NotStringRegex => {
+                let matched = regex::Regex::new(operand).ok()?.is_match(value);
+                Some(matched == (*self == LogicalOperator::StringRegex))
+            },
+            _ => None
+        }
+    }
+}
+
+/// Native conditions a non native operator was lowered into.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Lowered{
+    /// Conditions to be joined with AND.
+    All(Vec<Condition>),
+    /// Conditions to be joined with OR.
+    Any(Vec<Condition>),
+    /// A condition the server can't evaluate, to be checked on the client over the rows of a search.
+    Filter(Condition)
+}
+
+/// Escape the regex metacharacters of `s`.
+pub fn escape_regex(s : &str) -> String{
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars(){
+        if "\\.+*?()|[]{}^$#&-~".contains(c){
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Lower a condition into native ones.
+///
+/// - `In` on strings becomes a single anchored `StringRegex` alternation, on other types an OR chain of `Equal`
+/// - `NotIn` becomes an AND chain of `Diferent`
+/// - `Between` takes two values and becomes `HigherEquality` AND `LowerEquality`
+/// - `StartsWith` and `EndsWith` become an anchored `StringRegex`
+/// - the negated string operators can't be expressed natively and become a client-side filter
+///
+/// Condition chains have no grouping, so the builders only splice several conditions into a chain joined
+/// everywhere with the same operator as them.
+pub fn lower(column : String, operator : LogicalOperator, values : Vec<AlbaTypes>) -> Result<Lowered,Error>{
+    let single = |values : Vec<AlbaTypes>| -> Result<AlbaTypes,Error>{
+        let mut values = values;
+        if values.len() != 1{
+            return Err(Error::new(ErrorKind::InvalidInput, "The operator takes exactly one value"))
+        }
+        Ok(values.remove(0))
+    };
+    let string = |value : &AlbaTypes| -> Result<String,Error>{
+        match value{
+            AlbaTypes::String(s) => Ok(escape_regex(s)),
+            _ => Err(Error::new(ErrorKind::InvalidInput, "The operator only applies to strings"))
+        }
+    };
+    Ok(match operator{
+        op if op.is_native() => Lowered::All(vec![(column, op, single(values)?)]),
+        LogicalOperator::In => {
+            if values.is_empty(){
+                return Err(Error::new(ErrorKind::InvalidInput, "IN requires at least one value"))
+            }
+            if values.len() > 1 && values.iter().all(|v| matches!(v, AlbaTypes::String(_))){
+                let alternatives = values.iter().map(string).collect::<Result<Vec<String>,Error>>()?;
+                Lowered::All(vec![(column, LogicalOperator::StringRegex, AlbaTypes::String(format!("^(?:{})$", alternatives.join("|"))))])
+            }else if values.len() == 1{
+                Lowered::All(vec![(column, LogicalOperator::Equal, single(values)?)])
+            }else{
+                Lowered::Any(values.into_iter().map(|v| (column.clone(), LogicalOperator::Equal, v)).collect())
+            }
+        },
+        LogicalOperator::NotIn => {
+            if values.is_empty(){
+                return Err(Error::new(ErrorKind::InvalidInput, "NOT IN requires at least one value"))
+            }
+            Lowered::All(values.into_iter().map(|v| (column.clone(), LogicalOperator::Diferent, v)).collect())
+        },
+        LogicalOperator::Between => {
+            let mut values = values;
+            if values.len() != 2{
+                return Err(Error::new(ErrorKind::InvalidInput, "BETWEEN takes exactly two values"))
+            }
+            let high = values.remove(1);
+            let low = values.remove(0);
+            Lowered::All(vec![(column.clone(), LogicalOperator::HigherEquality, low), (column, LogicalOperator::LowerEquality, high)])
+        },
+        LogicalOperator::StartsWith => {
+            let pattern = format!("^{}", string(&single(values)?)?);
+            Lowered::All(vec![(column, LogicalOperator::StringRegex, AlbaTypes::String(pattern))])
+        },
+        LogicalOperator::EndsWith => {
+            let pattern = format!("{}$", string(&single(values)?)?);
+            Lowered::All(vec![(column, LogicalOperator::StringRegex, AlbaTypes::String(pattern))])
+        },
+        op => Lowered::Filter((column, op, single(values)?)),
+    })
+}
+
+
+impl fmt::Display for LogicalOperator{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        write!(f, "{}", self.spelling())
+    }
 }
 
 
@@ -60,6 +262,14 @@ macro_rules! lo {
     (6) => { LogicalOperator::StringContains };
     (7) => { LogicalOperator::StringContainsInsensitive };
     (8) => { LogicalOperator::StringRegex };
+    (9) => { LogicalOperator::In };
+    (10) => { LogicalOperator::NotIn };
+    (11) => { LogicalOperator::Between };
+    (12) => { LogicalOperator::StartsWith };
+    (13) => { LogicalOperator::EndsWith };
+    (14) => { LogicalOperator::NotStringContains };
+    (15) => { LogicalOperator::NotStringContainsInsensitive };
+    (16) => { LogicalOperator::NotStringRegex };
 
 
     ("=") => { LogicalOperator::Equal };
@@ -71,6 +281,13 @@ macro_rules! lo {
     ("&>") => { LogicalOperator::StringContains };
     ("&&>") => { LogicalOperator::StringContainsInsensitive };
     ("&&&>") => { LogicalOperator::StringRegex };
+    ("in") => { LogicalOperator::In };
+    ("!in") => { LogicalOperator::NotIn };
+    ("^>") => { LogicalOperator::StartsWith };
+    ("$>") => { LogicalOperator::EndsWith };
+    ("!&>") => { LogicalOperator::NotStringContains };
+    ("!&&>") => { LogicalOperator::NotStringContainsInsensitive };
+    ("!&&&>") => { LogicalOperator::NotStringRegex };
     
     (eq) => { LogicalOperator::Equal };
     (ne) => { LogicalOperator::Diferent };
@@ -81,6 +298,14 @@ macro_rules! lo {
     (contains) => { LogicalOperator::StringContains };
     (icontains) => { LogicalOperator::StringContainsInsensitive };
     (regex) => { LogicalOperator::StringRegex };
+    (in) => { LogicalOperator::In };
+    (not_in) => { LogicalOperator::NotIn };
+    (between) => { LogicalOperator::Between };
+    (starts_with) => { LogicalOperator::StartsWith };
+    (ends_with) => { LogicalOperator::EndsWith };
+    (not_contains) => { LogicalOperator::NotStringContains };
+    (not_icontains) => { LogicalOperator::NotStringContainsInsensitive };
+    (not_regex) => { LogicalOperator::NotStringRegex };
     
     (=) => { LogicalOperator::Equal };
     (!=) => { LogicalOperator::Diferent };
@@ -0,0 +1,232 @@
+use std::{sync::{atomic::{AtomicUsize, Ordering}, Arc}, time::Duration};
+#[cfg(any(feature="thread", feature="tokio", feature="asyncstd"))]
+use std::time::Instant;
+
+use crate::albastream::ErrorKind;
+#[cfg(any(feature="thread", feature="tokio", feature="asyncstd"))]
+use crate::{albastream::Error, commands::Commands, db_response::DBResponse};
+
+/// Receives the measurements of a client, see `Client::with_metrics`.
+///
+/// The methods are called on the request path and should only hand the values over (to counters, histograms or
+/// a channel). With the `metrics` feature, `Recorder` forwards them to the `metrics` crate.
+pub trait MetricsSink : Send + Sync{
+    /// A request was exchanged with the server, once per attempt, or failed fast without being sent.
+    fn request(&self, request : &RequestMetrics<'_>);
+    /// A connection was reopened after a failed exchange.
+    fn reconnect(&self, _host : &str, _success : bool){}
+    /// A heartbeat ping was sent.
+    fn heartbeat(&self, _host : &str, _latency : Duration, _success : bool){}
+    /// The connections exchanging a request (`busy`) out of the connections of the client (`open`).
+    fn connections(&self, _busy : usize, _open : usize){}
+}
+
+/// The measurements of one request.
+#[derive(Debug, Clone)]
+pub struct RequestMetrics<'a>{
+    /// The command kind, see `Commands::kind_name`.
+    pub command : &'static str,
+    /// The container label, following the `ContainerLabels` of the client.
+    pub container : Option<&'a str>,
+    /// The kind of the error, none when the request succeeded.
+    pub error : Option<ErrorKind>,
+    pub latency : Duration,
+    pub bytes_sent : usize,
+    pub bytes_received : usize,
+    /// The rows of the reply of a successful search.
+    pub rows : Option<usize>,
+}
+
+impl RequestMetrics<'_>{
+    /// `"ok"`, `"connection_error"`, `"empty_reply"`, `"invalid_input"`, `"circuit_open"` or `"server_error"`.
+    pub fn outcome(&self) -> &'static str{
+        match self.error{
+            None => "ok",
+            Some(ErrorKind::Connection) => "connection_error",
+            Some(ErrorKind::UnexpectedEof) => "empty_reply",
+            Some(ErrorKind::InvalidInput) => "invalid_input",
+            Some(ErrorKind::CircuitOpen) => "circuit_open",
+            Some(_) => "server_error",
+        }
+    }
+}
+
+/// Which containers label the request metrics, every distinct label being a series of the metrics backend.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ContainerLabels{
+    /// No container label.
+    None,
+    /// Every container labels its requests.
+    #[default]
+    All,
+    /// The listed containers label their requests, the others are labeled `"other"`.
+    Only(Vec<String>),
+}
+
+/// The metrics configuration of a client: the sink and the container labels.
+#[derive(Clone)]
+pub struct Metrics{
+    sink : Arc<dyn MetricsSink>,
+    containers : Arc<ContainerLabels>,
+    busy : Arc<AtomicUsize>,
+    open : Arc<AtomicUsize>,
+}
+
+impl Metrics{
+    pub fn new<S : MetricsSink + 'static>(sink : S) -> Self{
+        Metrics{sink:Arc::new(sink),containers:Arc::new(ContainerLabels::All),busy:Arc::new(AtomicUsize::new(0)),open:Arc::new(AtomicUsize::new(0))}
+    }
+    /// Replace the container labels, `ContainerLabels::All` by default.
+    pub fn containers(mut self, containers : ContainerLabels) -> Self{
+        self.containers = Arc::new(containers);
+        self
+    }
+    /// The label of `container` following the container labels.
+    pub fn label<'a>(&self, container : Option<&'a str>) -> Option<&'a str>{
+        match self.containers.as_ref(){
+            ContainerLabels::None => None,
+            ContainerLabels::All => container,
+            ContainerLabels::Only(listed) => container.map(|c| if listed.iter().any(|l| l == c) { c } else { "other" }),
+        }
+    }
+    pub fn sink(&self) -> &dyn MetricsSink{
+        self.sink.as_ref()
+    }
+    /// The connections exchanging a request and the connections open, over every client sharing these metrics.
+    pub fn connections(&self) -> (usize, usize){
+        (self.busy.load(Ordering::Relaxed), self.open.load(Ordering::Relaxed))
+    }
+}
+
+/// The start of an exchange, see `Metrics::exchange_started`. The connection stays busy until it is dropped, even
+/// when the exchange is cancelled before `Metrics::exchange_ended`.
+#[cfg(any(feature="thread", feature="tokio", feature="asyncstd"))]
+pub(crate) struct Exchange{
+    metrics : Metrics,
+    started : Instant,
+    command : u8,
+    container : Option<String>,
+    bytes_sent : usize,
+}
+
+#[cfg(any(feature="thread", feature="tokio", feature="asyncstd"))]
+impl Drop for Exchange{
+    fn drop(&mut self){
+        let busy = self.metrics.busy.fetch_sub(1, Ordering::Relaxed) - 1;
+        self.metrics.sink.connections(busy, self.metrics.open.load(Ordering::Relaxed));
+    }
+}
+
+/// Connections counted as open until it is dropped, see `Metrics::opened`.
+#[cfg(any(feature="thread", feature="tokio", feature="asyncstd"))]
+pub(crate) struct Opened{
+    metrics : Metrics,
+    connections : usize,
+}
+
+#[cfg(any(feature="thread", feature="tokio", feature="asyncstd"))]
+impl Drop for Opened{
+    fn drop(&mut self){
+        let open = self.metrics.open.fetch_sub(self.connections, Ordering::Relaxed) - self.connections;
+        self.metrics.sink.connections(self.metrics.busy.load(Ordering::Relaxed), open);
+    }
+}
+
+#[cfg(any(feature="thread", feature="tokio", feature="asyncstd"))]
+impl Metrics{
+    /// Count `connections` more open connections, until the returned guard is dropped.
+    pub(crate) fn opened(&self, connections : usize) -> Opened{
+        let open = self.open.fetch_add(connections, Ordering::Relaxed) + connections;
+        self.sink.connections(self.busy.load(Ordering::Relaxed), open);
+        Opened{metrics:self.clone(),connections}
+    }
+    pub(crate) fn exchange_started(&self, compiled_command : &[u8]) -> Exchange{
+        let busy = self.busy.fetch_add(1, Ordering::Relaxed) + 1;
+        self.sink.connections(busy, self.open.load(Ordering::Relaxed));
+        let container = match self.containers.as_ref(){
+            ContainerLabels::None => None,
+            _ => Commands::container_of(compiled_command),
+        };
+        Exchange{metrics:self.clone(),started:Instant::now(),command:compiled_command.first().copied().unwrap_or(u8::MAX),container,bytes_sent:compiled_command.len()}
+    }
+    pub(crate) fn exchange_ended(&self, exchange : Exchange, result : &Result<Vec<u8>,Error>){
+        let latency = exchange.started.elapsed();
+        self.sink.request(&RequestMetrics{
+            command: Commands::kind_name(exchange.command),
+            container: self.label(exchange.container.as_deref()),
+            error: result.as_ref().err().map(Error::kind),
+            latency,
+            bytes_sent: exchange.bytes_sent,
+            bytes_received: result.as_ref().map_or(0, |payload| payload.len() + 1),
+            rows: match (exchange.command, result){
+                (5, Ok(payload)) => Some(DBResponse::iter_rows(payload).take_while(Result::is_ok).count()),
+                _ => None,
+            },
+        });
+    }
+    /// Report a request failing without being sent, e.g. while the circuit breaker is open.
+    pub(crate) fn rejected(&self, compiled_command : &[u8], error : &Error){
+        let container = match self.containers.as_ref(){
+            ContainerLabels::None => None,
+            _ => Commands::container_of(compiled_command),
+        };
+        self.sink.request(&RequestMetrics{
+            command: Commands::kind_name(compiled_command.first().copied().unwrap_or(u8::MAX)),
+            container: self.label(container.as_deref()),
+            error: Some(error.kind()),
+            latency: Duration::ZERO,
+            bytes_sent: 0,
+            bytes_received: 0,
+            rows: None,
+        });
+    }
+    pub(crate) fn reconnected(&self, host : &str, success : bool){
+        self.sink.reconnect(host, success);
+    }
+    pub(crate) fn heartbeat(&self, host : &str, latency : Duration, success : bool){
+        self.sink.heartbeat(host, latency, success);
+    }
+}
+
+/// A `MetricsSink` recording into the `metrics` crate, to be exported by any of its recorders (Prometheus,
+/// StatsD...).
+///
+/// Counters `tytodb_requests_total` (labels `command`, `container`, `outcome`), `tytodb_bytes_sent_total`,
+/// `tytodb_bytes_received_total`, `tytodb_reconnects_total` (label `outcome`) and
+/// `tytodb_heartbeat_failures_total`; histograms `tytodb_request_duration_seconds`, `tytodb_rows_returned` and
+/// `tytodb_heartbeat_duration_seconds`; gauges `tytodb_connections_busy` and `tytodb_connections_open`. The
+/// `container` label is left out of the requests without one.
+#[cfg(feature="metrics")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Recorder;
+
+#[cfg(feature="metrics")]
+impl MetricsSink for Recorder{
+    fn request(&self, request : &RequestMetrics<'_>){
+        let mut labels = vec![::metrics::Label::new("command", request.command)];
+        if let Some(container) = request.container{
+            labels.push(::metrics::Label::new("container", container.to_string()));
+        }
+        ::metrics::counter!("tytodb_bytes_sent_total", labels.clone()).increment(request.bytes_sent as u64);
+        ::metrics::counter!("tytodb_bytes_received_total", labels.clone()).increment(request.bytes_received as u64);
+        ::metrics::histogram!("tytodb_request_duration_seconds", labels.clone()).record(request.latency.as_secs_f64());
+        if let Some(rows) = request.rows{
+            ::metrics::histogram!("tytodb_rows_returned", labels.clone()).record(rows as f64);
+        }
+        labels.push(::metrics::Label::new("outcome", request.outcome()));
+        ::metrics::counter!("tytodb_requests_total", labels).increment(1);
+    }
+    fn reconnect(&self, _host : &str, success : bool){
+        ::metrics::counter!("tytodb_reconnects_total", "outcome" => if success { "ok" } else { "failed" }).increment(1);
+    }
+    fn heartbeat(&self, _host : &str, latency : Duration, success : bool){
+        ::metrics::histogram!("tytodb_heartbeat_duration_seconds").record(latency.as_secs_f64());
+        if !success{
+            ::metrics::counter!("tytodb_heartbeat_failures_total").increment(1);
+        }
+    }
+    fn connections(&self, busy : usize, open : usize){
+        ::metrics::gauge!("tytodb_connections_busy").set(busy as f64);
+        ::metrics::gauge!("tytodb_connections_open").set(open as f64);
+    }
+}
@@ -0,0 +1,535 @@
+use std::{fmt, sync::Arc, time::{SystemTime, UNIX_EPOCH}};
+
+use crate::{albastream::{CompiledAlba, Error, ErrorKind}, bulk::BulkInsert, commands::{Commands, Search}, db_response::DBResponse, handler::{CreateContainerBuilder, CreateRowBuilder, DeleteContainerBuilder}, parser::{self, quote_name, literal}, types::{convert_value, column_type_name, AlbaTypes, MEDIUM_STRING, UBIGINT}};
+
+/// The container recording the applied migrations, created by the first `Client::migrate`.
+pub const MIGRATIONS_CONTAINER : &str = "_migrations";
+/// How the server words a search of a container that doesn't exist, lowercased. The server's source isn't part of
+/// this crate and these phrasings aren't checked against it: a missing container worded otherwise makes `migrate`
+/// fail with the server's error instead of creating `MIGRATIONS_CONTAINER`, which then has to be created with
+/// `Migrations::create_bookkeeping`.
+const MISSING_CONTAINER : [&str; 4] = ["does not exist", "doesn't exist", "not found", "no such container"];
+const MIGRATIONS_COLUMNS : [(&str,u8); 4] = [("version", UBIGINT), ("name", MEDIUM_STRING), ("checksum", UBIGINT), ("applied_at", UBIGINT)];
+
+type RowTransform = Arc<dyn Fn(Vec<AlbaTypes>) -> Result<Vec<AlbaTypes>,Error> + Send + Sync>;
+
+fn schema(columns : &[(&str,u8)]) -> Vec<(String,u8)>{
+    columns.iter().map(|(c, t)| (c.to_string(), *t)).collect()
+}
+
+fn describe_columns(columns : &[(String,u8)]) -> String{
+    columns.iter().map(|(c, t)| format!("{} {}", quote_name(c), column_type_name(*t).map(str::to_string).unwrap_or(t.to_string()))).collect::<Vec<String>>().join(", ")
+}
+
+fn create_container(container : &str, columns : &[(String,u8)]) -> Result<CompiledAlba,Error>{
+    columns.iter().fold(CreateContainerBuilder::new().put_container(container.to_string()), |builder, (c, t)| builder.insert_header(c.clone(), *t)).finish()
+}
+
+/// Where a column of a `CopyTransform` target takes its values.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColumnSource{
+    /// The value of a source column, converted to the target column type.
+    Column(String),
+    /// The same value for every row, converted to the target column type.
+    Value(AlbaTypes),
+}
+
+/// A copy of every row of a container into a new container, for adding, renaming or retyping columns.
+///
+/// The target container is created with `to_columns`, then the rows of the source, searched on `from_columns`,
+/// are inserted in chunks. By default a target column takes the source column of the same name; `column` maps it
+/// to another source column or a constant, and values are converted to the target column types with
+/// `convert_value`. `map_rows` replaces the mapping with a Rust function of the source row.
+///
+/// TytoDB can't rename a container, so changing a container in place takes a copy to a new container, dropping
+/// the old one, and (if the name matters) a copy back.
+#[derive(Clone)]
+pub struct CopyTransform{
+    from : String,
+    from_columns : Vec<(String,u8)>,
+    to : String,
+    to_columns : Vec<(String,u8)>,
+    sources : Vec<(String,ColumnSource)>,
+    transform : Option<RowTransform>,
+    bulk : BulkInsert,
+}
+
+impl CopyTransform{
+    pub fn new(from : &str, from_columns : &[(&str,u8)], to : &str, to_columns : &[(&str,u8)]) -> Self{
+        CopyTransform{
+            from: from.to_string(),
+            from_columns: schema(from_columns),
+            to: to.to_string(),
+            to_columns: schema(to_columns),
+            sources: Vec::new(),
+            transform: None,
+            bulk: BulkInsert::new(to, to_columns.iter().map(|(c, _)| c.to_string()).collect()),
+        }
+    }
+    /// Fill the target column `column` from `source` instead of the source column of the same name.
+    pub fn column(mut self, column : &str, source : ColumnSource) -> Self{
+        self.sources.retain(|(c, _)| c != column);
+        self.sources.push((column.to_string(), source));
+        self
+    }
+    /// Build every target row from the source row (in `from_columns` order) with `transform`.
+    pub fn map_rows<F : Fn(Vec<AlbaTypes>) -> Result<Vec<AlbaTypes>,Error> + Send + Sync + 'static>(mut self, transform : F) -> Self{
+        self.transform = Some(Arc::new(transform));
+        self
+    }
+    /// The chunking of the insertions, its container and columns are those of the target.
+    pub fn bulk(mut self, bulk : BulkInsert) -> Self{
+        let (to, columns) = (self.bulk.container, self.bulk.columns);
+        self.bulk = bulk.concurrency(1).transaction(false);
+        self.bulk.container = to;
+        self.bulk.columns = columns;
+        self
+    }
+    pub fn insertions(&self) -> &BulkInsert{
+        &self.bulk
+    }
+    /// The search of the rows to copy.
+    pub fn search(&self) -> Result<CompiledAlba,Error>{
+        Commands::Search(Search{
+            container: self.from.clone(),
+            conditions: (Vec::new(), Vec::new()),
+            col_nam: self.from_columns.iter().map(|(c, _)| c.clone()).collect(),
+        }).compile()
+    }
+    /// The creation of the target container.
+    pub fn create_target(&self) -> Result<CompiledAlba,Error>{
+        create_container(&self.to, &self.to_columns)
+    }
+    /// The target rows of the encoded rows of `payload`, the reply of `search`.
+    pub fn rows(&self, payload : &[u8]) -> Result<Vec<Vec<AlbaTypes>>,Error>{
+        let resolved = self.resolve()?;
+        let mut rows = Vec::new();
+        for (index, row) in DBResponse::iter_rows(payload).enumerate(){
+            let source = row?.0;
+            if source.len() != self.from_columns.len(){
+                return Err(Error::new(ErrorKind::InvalidInput, &format!("Row {} of `{}` doesn't have a value per copied column", index, self.from)))
+            }
+            let row = match &self.transform{
+                Some(transform) => transform(source)?,
+                None => resolved.iter().map(|r| match r{
+                    Resolved::Column(i) => source[*i].clone(),
+                    Resolved::Value(v) => v.clone(),
+                }).collect(),
+            };
+            if row.len() != self.to_columns.len(){
+                return Err(Error::new(ErrorKind::InvalidInput, &format!("Row {} of `{}` has {} values for {} columns", index, self.to, row.len(), self.to_columns.len())))
+            }
+            let row = row.into_iter().zip(self.to_columns.iter()).map(|(v, (c, t))| convert_value(v, *t).map_err(|e| Error::new(e.kind(), &format!("Row {} of `{}`, column `{}`: {}", index, self.from, c, e.message())))).collect::<Result<Vec<AlbaTypes>,Error>>()?;
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+    fn resolve(&self) -> Result<Vec<Resolved>,Error>{
+        if self.transform.is_some(){
+            return Ok(Vec::new())
+        }
+        self.to_columns.iter().map(|(column, _)| {
+            let source = self.sources.iter().find(|(c, _)| c == column).map(|(_, s)| s.clone()).unwrap_or_else(|| ColumnSource::Column(column.clone()));
+            match source{
+                ColumnSource::Value(v) => Ok(Resolved::Value(v)),
+                ColumnSource::Column(c) => match self.from_columns.iter().position(|(f, _)| *f == c){
+                    Some(i) => Ok(Resolved::Column(i)),
+                    None => Err(Error::new(ErrorKind::InvalidInput, &format!("The column `{}` of `{}` has no source, `{}` has no column `{}`", column, self.to, self.from, c))),
+                },
+            }
+        }).collect()
+    }
+}
+
+enum Resolved{
+    Column(usize),
+    Value(AlbaTypes),
+}
+
+impl fmt::Debug for CopyTransform{
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result{
+        write!(f, "{}", self)
+    }
+}
+
+/// The text form of the copy, as parsed by `Migration::from_text`. A `map_rows` function is written as
+/// `WITH <rust function>`.
+impl fmt::Display for CopyTransform{
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result{
+        write!(f, "COPY {} ({}) INTO {} (", quote_name(&self.from), describe_columns(&self.from_columns), quote_name(&self.to))?;
+        for (i, (column, column_type)) in self.to_columns.iter().enumerate(){
+            if i > 0{
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", describe_columns(&[(column.clone(), *column_type)]))?;
+            match self.sources.iter().find(|(c, _)| c == column).map(|(_, s)| s){
+                Some(ColumnSource::Column(c)) => write!(f, " FROM {}", quote_name(c))?,
+                Some(ColumnSource::Value(v)) => write!(f, " DEFAULT {}", literal(v))?,
+                None => {},
+            }
+        }
+        write!(f, ")")?;
+        if self.transform.is_some(){
+            write!(f, " WITH <rust function>")?;
+        }
+        Ok(())
+    }
+}
+
+/// A step of a `Migration`.
+#[derive(Debug, Clone)]
+pub enum Step{
+    CreateContainer{container : String, columns : Vec<(String,u8)>},
+    DropContainer{container : String},
+    CopyTransform(CopyTransform),
+    /// Any other command, e.g. an `EditRow` filling a column.
+    Execute(Commands),
+}
+
+impl Step{
+    pub fn create_container(container : &str, columns : &[(&str,u8)]) -> Self{
+        Step::CreateContainer{container:container.to_string(),columns:schema(columns)}
+    }
+    pub fn drop_container(container : &str) -> Self{
+        Step::DropContainer{container:container.to_string()}
+    }
+    /// The command of the step, none for a copy which runs a search and insertions, see `CopyTransform`.
+    pub fn compile(&self) -> Result<Option<CompiledAlba>,Error>{
+        Ok(Some(match self{
+            Step::CreateContainer{container, columns} => create_container(container, columns)?,
+            Step::DropContainer{container} => DeleteContainerBuilder::new().put_container(container.clone()).finish()?,
+            Step::CopyTransform(_) => return Ok(None),
+            Step::Execute(command) => command.compile()?,
+        }))
+    }
+    fn checksum_bytes(&self) -> Result<Vec<u8>,Error>{
+        Ok(match self{
+            Step::Execute(command) => command.compile()?,
+            step => step.to_string().into_bytes(),
+        })
+    }
+}
+
+impl From<Commands> for Step{
+    fn from(command : Commands) -> Step{
+        match command{
+            Commands::CreateContainer(c) => Step::CreateContainer{columns:c.col_nam.into_iter().zip(c.col_val).collect(),container:c.name},
+            Commands::DeleteContainer(d) => Step::DropContainer{container:d.container},
+            command => Step::Execute(command),
+        }
+    }
+}
+
+impl fmt::Display for Step{
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self{
+            Step::CreateContainer{container, columns} => write!(f, "CREATE CONTAINER {} ({})", quote_name(container), describe_columns(columns)),
+            Step::DropContainer{container} => write!(f, "DROP CONTAINER {}", quote_name(container)),
+            Step::CopyTransform(copy) => write!(f, "{}", copy),
+            Step::Execute(command) => write!(f, "{}", command),
+        }
+    }
+}
+
+/// A versioned list of steps, applied in order by `Client::migrate` and then recorded in `MIGRATIONS_CONTAINER`.
+///
+/// The steps of a migration are not atomic: when one fails the migration isn't recorded and the steps before it
+/// stay applied, they have to be undone by hand before migrating again. Neither are the steps and the `record`
+/// that follows them: when the steps succeed but the record fails (e.g. the connection drops), the migration stays
+/// pending and the next `migrate` runs its steps again, failing on the containers they already created unless
+/// they are dropped first.
+#[derive(Debug, Clone)]
+pub struct Migration{
+    version : u64,
+    name : String,
+    steps : Vec<Step>,
+}
+
+impl Migration{
+    pub fn new(version : u64, name : &str) -> Self{
+        Migration{version,name:name.to_string(),steps:Vec::new()}
+    }
+    /// A migration of the statements of `script`, see `parser::parse_migration`.
+    pub fn from_text(version : u64, name : &str, script : &str) -> Result<Self,Error>{
+        Ok(Migration{version,name:name.to_string(),steps:parser::parse_migration(script)?})
+    }
+    pub fn step(mut self, step : Step) -> Self{
+        self.steps.push(step);
+        self
+    }
+    pub fn create_container(self, container : &str, columns : &[(&str,u8)]) -> Self{
+        self.step(Step::create_container(container, columns))
+    }
+    pub fn drop_container(self, container : &str) -> Self{
+        self.step(Step::drop_container(container))
+    }
+    pub fn copy(self, copy : CopyTransform) -> Self{
+        self.step(Step::CopyTransform(copy))
+    }
+    pub fn execute(self, command : Commands) -> Self{
+        self.step(Step::Execute(command))
+    }
+    pub fn version(&self) -> u64{
+        self.version
+    }
+    pub fn name(&self) -> &str{
+        &self.name
+    }
+    pub fn steps(&self) -> &[Step]{
+        &self.steps
+    }
+    /// A FNV-1a hash of the steps, recorded to detect a migration edited after it was applied.
+    pub fn checksum(&self) -> Result<u64,Error>{
+        let mut hash = 0xcbf29ce484222325u64;
+        for step in self.steps.iter(){
+            for byte in step.checksum_bytes()?.into_iter().chain([0]){
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+        }
+        Ok(hash)
+    }
+    /// The error of step number `step` (starting at 0) of this migration.
+    pub fn step_failed(&self, step : usize, error : &Error) -> Error{
+        Error::new(error.kind(), &format!("Migration {} ({}) failed at step {} `{}`: {}", self.version, self.name, step + 1, self.steps[step], error.message()))
+    }
+    /// The `CreateRow` recording this migration as applied now.
+    pub fn record(&self) -> Result<CompiledAlba,Error>{
+        let applied_at = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        CreateRowBuilder::new()
+            .put_container(MIGRATIONS_CONTAINER.to_string())
+            .insert_value("version".to_string(), AlbaTypes::U64(self.version))
+            .insert_value("name".to_string(), AlbaTypes::String(self.name.clone()))
+            .insert_value("checksum".to_string(), AlbaTypes::U64(self.checksum()?))
+            .insert_value("applied_at".to_string(), AlbaTypes::U64(applied_at))
+            .finish()
+    }
+}
+
+/// A migration recorded in `MIGRATIONS_CONTAINER`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppliedMigration{
+    pub version : u64,
+    pub name : String,
+    pub checksum : u64,
+    /// Unix time in seconds.
+    pub applied_at : u64,
+}
+
+/// Where a migration stands, see `Migrations::status`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrationState{
+    Pending,
+    Applied{applied_at : u64},
+    /// Applied, but its steps changed since.
+    Modified{applied_at : u64},
+    /// Recorded as applied but not part of the migrations.
+    Unknown{applied_at : u64},
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationStatus{
+    pub version : u64,
+    pub name : String,
+    pub state : MigrationState,
+}
+
+/// The state of every migration, known or recorded, in version order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StatusReport{
+    pub migrations : Vec<MigrationStatus>,
+}
+
+impl StatusReport{
+    pub fn pending(&self) -> impl Iterator<Item = &MigrationStatus>{
+        self.migrations.iter().filter(|m| m.state == MigrationState::Pending)
+    }
+    /// Whether every migration is applied as it is.
+    pub fn is_up_to_date(&self) -> bool{
+        self.migrations.iter().all(|m| matches!(m.state, MigrationState::Applied{..}))
+    }
+}
+
+impl fmt::Display for StatusReport{
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result{
+        for m in self.migrations.iter(){
+            let state = match m.state{
+                MigrationState::Pending => "pending".to_string(),
+                MigrationState::Applied{applied_at} => format!("applied at {}", applied_at),
+                MigrationState::Modified{applied_at} => format!("applied at {}, modified since", applied_at),
+                MigrationState::Unknown{applied_at} => format!("applied at {}, unknown", applied_at),
+            };
+            writeln!(f, "{:>6} {} ({})", m.version, m.name, state)?;
+        }
+        Ok(())
+    }
+}
+
+/// The outcome of `Client::migrate`: the migrations applied, or that would be with a dry run, with their steps.
+#[derive(Debug, Clone, Default)]
+pub struct MigrationReport{
+    pub dry_run : bool,
+    /// Version, name and steps of every migration, in the order they were applied.
+    pub applied : Vec<(u64,String,Vec<String>)>,
+}
+
+impl MigrationReport{
+    /// Add `migration` to the applied migrations.
+    pub fn push(&mut self, migration : &Migration){
+        self.applied.push((migration.version, migration.name.clone(), migration.steps.iter().map(Step::to_string).collect()));
+    }
+}
+
+impl fmt::Display for MigrationReport{
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result{
+        if self.applied.is_empty(){
+            return writeln!(f, "Nothing to migrate")
+        }
+        for (version, name, steps) in self.applied.iter(){
+            writeln!(f, "{} {} ({})", if self.dry_run { "Would apply" } else { "Applied" }, version, name)?;
+            for step in steps{
+                writeln!(f, "  {}", step)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An ordered set of migrations, see `Client::migrate`.
+#[derive(Debug, Clone, Default)]
+pub struct Migrations{
+    migrations : Vec<Migration>,
+}
+
+impl Migrations{
+    pub fn new() -> Self{
+        Self::default()
+    }
+    /// Add a migration, kept in version order. Versions must be unique.
+    pub fn migration(mut self, migration : Migration) -> Result<Self,Error>{
+        if self.migrations.iter().any(|m| m.version == migration.version){
+            return Err(Error::new(ErrorKind::InvalidInput, &format!("Migration {} is defined twice", migration.version)))
+        }
+        let position = self.migrations.partition_point(|m| m.version < migration.version);
+        self.migrations.insert(position, migration);
+        Ok(self)
+    }
+    /// Parse a migrations file: every migration starts with a `-- migration <version> <name>` line, followed by
+    /// its statements (see `parser::parse_migration`).
+    ///
+    /// ```text
+    /// -- migration 1 create users
+    /// CREATE CONTAINER users (id BIGINT, name SMALL_STRING);
+    /// -- migration 2 longer names
+    /// COPY users (id BIGINT, name SMALL_STRING) INTO users_v2 (id BIGINT, name MEDIUM_STRING, active BOOL DEFAULT true);
+    /// DROP CONTAINER users
+    /// ```
+    pub fn parse(text : &str) -> Result<Self,Error>{
+        let mut migrations = Migrations::new();
+        let mut current : Option<(u64, String, usize, String)> = None;
+        for (number, line) in text.lines().enumerate().chain([(usize::MAX, "-- migration")]){
+            let Some(header) = line.trim().strip_prefix("-- migration") else {
+                match current.as_mut(){
+                    Some((_, _, _, script)) => {
+                        script.push_str(line);
+                        script.push('\n');
+                    },
+                    None if line.trim().is_empty() || line.trim().starts_with("--") => {},
+                    None => return Err(Error::new(ErrorKind::InvalidInput, &format!("line {}: a statement before the first `-- migration` line", number + 1))),
+                }
+                continue
+            };
+            if let Some((version, name, first_line, script)) = current.take(){
+                let steps = parser::parse_migration(&script).map_err(|mut e| {
+                    e.line += first_line;
+                    e
+                })?;
+                migrations = migrations.migration(Migration{version,name,steps})?;
+            }
+            if number == usize::MAX{
+                break
+            }
+            let header = header.trim();
+            let (version, name) = header.split_once(char::is_whitespace).unwrap_or((header, ""));
+            let version = version.parse::<u64>().map_err(|_| Error::new(ErrorKind::InvalidInput, &format!("line {}: expected `-- migration <version> <name>`", number + 1)))?;
+            current = Some((version, name.trim().to_string(), number + 1, String::new()));
+        }
+        Ok(migrations)
+    }
+    pub fn migrations(&self) -> &[Migration]{
+        &self.migrations
+    }
+    /// The creation of `MIGRATIONS_CONTAINER`.
+    pub fn create_bookkeeping() -> Result<CompiledAlba,Error>{
+        create_container(MIGRATIONS_CONTAINER, &schema(&MIGRATIONS_COLUMNS))
+    }
+    /// The search of the applied migrations, read by `applied`.
+    pub fn search_applied() -> Result<CompiledAlba,Error>{
+        Commands::Search(Search{
+            container: MIGRATIONS_CONTAINER.to_string(),
+            conditions: (Vec::new(), Vec::new()),
+            col_nam: MIGRATIONS_COLUMNS.iter().map(|(c, _)| c.to_string()).collect(),
+        }).compile()
+    }
+    /// Whether `error`, the failure of `search_applied`, is the server reporting that `MIGRATIONS_CONTAINER` doesn't
+    /// exist. Any other failure of the search is not taken as a missing container.
+    pub fn is_missing_bookkeeping(error : &Error) -> bool{
+        let message = error.message().to_lowercase();
+        error.kind() == ErrorKind::Other && MISSING_CONTAINER.iter().any(|missing| message.contains(missing))
+    }
+    /// The applied migrations of `payload`, the reply of `search_applied`, in version order.
+    pub fn applied(payload : &[u8]) -> Result<Vec<AppliedMigration>,Error>{
+        let mut applied = Vec::new();
+        for row in DBResponse::iter_rows(payload){
+            match row?.0.as_slice(){
+                [AlbaTypes::U64(version), AlbaTypes::String(name), AlbaTypes::U64(checksum), AlbaTypes::U64(applied_at)] => {
+                    applied.push(AppliedMigration{version:*version,name:name.clone(),checksum:*checksum,applied_at:*applied_at});
+                },
+                _ => return Err(Error::new(ErrorKind::InvalidInput, &format!("`{}` holds a malformed row", MIGRATIONS_CONTAINER))),
+            }
+        }
+        applied.sort_by_key(|a| a.version);
+        Ok(applied)
+    }
+    pub fn status(&self, applied : &[AppliedMigration]) -> Result<StatusReport,Error>{
+        let mut report = StatusReport::default();
+        for migration in self.migrations.iter(){
+            let state = match applied.iter().find(|a| a.version == migration.version){
+                None => MigrationState::Pending,
+                Some(a) if a.checksum == migration.checksum()? => MigrationState::Applied{applied_at:a.applied_at},
+                Some(a) => MigrationState::Modified{applied_at:a.applied_at},
+            };
+            report.migrations.push(MigrationStatus{version:migration.version,name:migration.name.clone(),state});
+        }
+        for a in applied.iter().filter(|a| !self.migrations.iter().any(|m| m.version == a.version)){
+            report.migrations.push(MigrationStatus{version:a.version,name:a.name.clone(),state:MigrationState::Unknown{applied_at:a.applied_at}});
+        }
+        report.migrations.sort_by_key(|m| m.version);
+        Ok(report)
+    }
+    /// The migrations to apply, in version order. Fails when an applied migration was modified since, migrating
+    /// would build on a schema the migrations no longer describe.
+    pub fn pending(&self, applied : &[AppliedMigration]) -> Result<Vec<&Migration>,Error>{
+        let status = self.status(applied)?;
+        if let Some(m) = status.migrations.iter().find(|m| matches!(m.state, MigrationState::Modified{..})){
+            return Err(Error::new(ErrorKind::InvalidInput, &format!("Migration {} ({}) changed since it was applied", m.version, m.name)))
+        }
+        Ok(self.migrations.iter().filter(|m| !applied.iter().any(|a| a.version == m.version)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[test]
+    fn only_missing_container_errors_are_missing_bookkeeping(){
+        for message in ["Container `_migrations` does not exist", "the container _migrations doesn't exist", "Container not found", "No such container: _migrations"]{
+            assert!(Migrations::is_missing_bookkeeping(&Error::new(ErrorKind::Other, message)), "{}", message);
+        }
+        assert!(!Migrations::is_missing_bookkeeping(&Error::new(ErrorKind::Other, "The column version has an invalid type")));
+        // only the replies of the server are taken, not the failures of the connection
+        assert!(!Migrations::is_missing_bookkeeping(&Error::new(ErrorKind::Connection, "container not found")));
+        assert!(!Migrations::is_missing_bookkeeping(&Error::new(ErrorKind::UnexpectedEof, "Empty response")));
+    }
+}
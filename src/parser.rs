@@ -0,0 +1,815 @@
+use std::{fmt, str::FromStr};
+
+use crate::{
+    albastream::{Error, ErrorKind},
+    commands::{Batch, BatchCreateRows, Commands, Commit, CreateContainer, CreateRow, DeleteContainer, DeleteRow, Rollback},
+    handler::{BatchingItem, DeleteRowBuilder, EditRowBuilder, SearchBuilder},
+    logical_operators::{LogicalOperator, Lowered, lower},
+    migrate::{ColumnSource, CopyTransform, Step},
+    types::{AlbaTypes, column_type_id},
+};
+
+/// A syntax error of the text query language, located by its 1-based line and column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(e: ParseError) -> Error {
+        Error::new(ErrorKind::InvalidInput, &e.to_string())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// A bare word: keyword, name, operator word or `true`/`false`.
+    Word(String),
+    /// A `"double quoted"` name.
+    Quoted(String),
+    /// A `'single quoted'` string.
+    Str(String),
+    /// A `x'00ff'` bytes literal.
+    Hex(Vec<u8>),
+    Number(String),
+    Symbol(String),
+}
+
+#[derive(Debug, Clone)]
+struct Spanned {
+    token: Token,
+    line: usize,
+    column: usize,
+}
+
+const OPERATOR_CHARS: &str = "!<>=&^$";
+
+fn tokenize(input: &str) -> Result<Vec<Spanned>, ParseError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let (mut i, mut line, mut column) = (0, 1, 1);
+    // advances over `n` chars keeping track of the position
+    let advance = |i: &mut usize, line: &mut usize, column: &mut usize, n: usize| {
+        for _ in 0..n {
+            if chars[*i] == '\n' {
+                *line += 1;
+                *column = 1;
+            } else {
+                *column += 1;
+            }
+            *i += 1;
+        }
+    };
+    while i < chars.len() {
+        let c = chars[i];
+        let (start_line, start_column) = (line, column);
+        let error = |message: &str| ParseError { line: start_line, column: start_column, message: message.to_string() };
+        if c.is_whitespace() {
+            advance(&mut i, &mut line, &mut column, 1);
+            continue;
+        }
+        if c == '-' && chars.get(i + 1) == Some(&'-') {
+            while i < chars.len() && chars[i] != '\n' {
+                advance(&mut i, &mut line, &mut column, 1);
+            }
+            continue;
+        }
+        let (token, len) = if (c == 'x' || c == 'X') && chars.get(i + 1) == Some(&'\'') {
+            let (text, len) = quoted(&chars[i + 1..], '\'').ok_or_else(|| error("Unterminated bytes literal"))?;
+            if text.len() % 2 != 0 {
+                return Err(error("A bytes literal needs an even number of hex digits"));
+            }
+            let mut bytes = Vec::with_capacity(text.len() / 2);
+            for pair in text.as_bytes().chunks(2) {
+                match std::str::from_utf8(pair).ok().and_then(|p| u8::from_str_radix(p, 16).ok()) {
+                    Some(b) => bytes.push(b),
+                    None => return Err(error("Invalid hex digit in bytes literal")),
+                }
+            }
+            (Token::Hex(bytes), len + 1)
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let len = chars[i..].iter().take_while(|c| c.is_ascii_alphanumeric() || **c == '_').count();
+            (Token::Word(chars[i..i + len].iter().collect()), len)
+        } else if c == '\'' {
+            let (text, len) = quoted(&chars[i..], '\'').ok_or_else(|| error("Unterminated string"))?;
+            (Token::Str(text), len)
+        } else if c == '"' {
+            let (text, len) = quoted(&chars[i..], '"').ok_or_else(|| error("Unterminated quoted name"))?;
+            (Token::Quoted(text), len)
+        } else if c.is_ascii_digit() || (c == '-' && chars.get(i + 1).is_some_and(|c| c.is_ascii_digit())) {
+            let mut len = 1;
+            while let Some(n) = chars.get(i + len) {
+                let exponent_sign = (*n == '+' || *n == '-') && matches!(chars[i + len - 1], 'e' | 'E');
+                if n.is_ascii_alphanumeric() || *n == '_' || *n == '.' || exponent_sign {
+                    len += 1;
+                } else {
+                    break;
+                }
+            }
+            (Token::Number(chars[i..i + len].iter().collect()), len)
+        } else if "(),;*".contains(c) {
+            (Token::Symbol(c.to_string()), 1)
+        } else if OPERATOR_CHARS.contains(c) {
+            let len = chars[i..].iter().take_while(|c| OPERATOR_CHARS.contains(**c)).count();
+            (Token::Symbol(chars[i..i + len].iter().collect()), len)
+        } else {
+            return Err(error(&format!("Unexpected character {:?}", c)));
+        };
+        tokens.push(Spanned { token, line: start_line, column: start_column });
+        advance(&mut i, &mut line, &mut column, len);
+    }
+    Ok(tokens)
+}
+
+/// Reads a literal enclosed by `quote` at the start of `chars`, a doubled quote standing for itself.
+/// Returns the content and the number of chars consumed, quotes included.
+fn quoted(chars: &[char], quote: char) -> Option<(String, usize)> {
+    let mut text = String::new();
+    let mut i = 1;
+    loop {
+        match chars.get(i) {
+            Some(c) if *c == quote => {
+                if chars.get(i + 1) == Some(&quote) {
+                    text.push(quote);
+                    i += 2;
+                } else {
+                    return Some((text, i + 1));
+                }
+            }
+            Some(c) => {
+                text.push(*c);
+                i += 1;
+            }
+            None => return None,
+        }
+    }
+}
+
+fn number(text: &str) -> Option<AlbaTypes> {
+    let text = text.replace('_', "");
+    let split = text.find(['u', 'i', 'f']).unwrap_or(text.len());
+    let (digits, suffix) = text.split_at(split);
+    Some(match suffix {
+        "u8" => AlbaTypes::U8(digits.parse().ok()?),
+        "u16" => AlbaTypes::U16(digits.parse().ok()?),
+        "u32" => AlbaTypes::U32(digits.parse().ok()?),
+        "u64" => AlbaTypes::U64(digits.parse().ok()?),
+        "u128" => AlbaTypes::U128(digits.parse().ok()?),
+        "i32" => AlbaTypes::I32(digits.parse().ok()?),
+        "i64" => AlbaTypes::I64(digits.parse().ok()?),
+        "i128" => AlbaTypes::I128(digits.parse().ok()?),
+        "f32" => AlbaTypes::F32(digits.parse().ok()?),
+        "f64" => AlbaTypes::F64(digits.parse().ok()?),
+        "" if digits.contains(['.', 'e', 'E']) => AlbaTypes::F64(digits.parse().ok()?),
+        "" => {
+            if let Ok(v) = digits.parse::<i64>() {
+                AlbaTypes::I64(v)
+            } else if let Ok(v) = digits.parse::<i128>() {
+                AlbaTypes::I128(v)
+            } else {
+                AlbaTypes::U128(digits.parse().ok()?)
+            }
+        }
+        _ => return None,
+    })
+}
+
+/// A condition as written, before being lowered into the builders.
+struct ParsedCondition {
+    column: String,
+    operator: LogicalOperator,
+    values: Vec<AlbaTypes>,
+    and: bool,
+    line: usize,
+    column_number: usize,
+}
+
+struct Parser {
+    tokens: Vec<Spanned>,
+    position: usize,
+    end: (usize, usize),
+}
+
+impl Parser {
+    fn new(input: &str) -> Result<Parser, ParseError> {
+        let tokens = tokenize(input)?;
+        let line = input.lines().count().max(1);
+        let column = input.lines().last().map(|l| l.chars().count()).unwrap_or(0) + 1;
+        Ok(Parser { tokens, position: 0, end: (line, column) })
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|t| &t.token)
+    }
+    fn location(&self) -> (usize, usize) {
+        match self.tokens.get(self.position) {
+            Some(t) => (t.line, t.column),
+            None => self.end,
+        }
+    }
+    fn error<T>(&self, message: &str) -> Result<T, ParseError> {
+        let (line, column) = self.location();
+        Err(ParseError { line, column, message: message.to_string() })
+    }
+    fn describe(&self) -> String {
+        match self.peek() {
+            Some(Token::Word(w)) => format!("`{}`", w),
+            Some(Token::Quoted(q)) => format!("\"{}\"", q),
+            Some(Token::Str(s)) => format!("'{}'", s),
+            Some(Token::Hex(_)) => "a bytes literal".to_string(),
+            Some(Token::Number(n)) => format!("`{}`", n),
+            Some(Token::Symbol(s)) => format!("`{}`", s),
+            None => "the end of the input".to_string(),
+        }
+    }
+    fn unexpected<T>(&self, expected: &str) -> Result<T, ParseError> {
+        self.error(&format!("Expected {}, found {}", expected, self.describe()))
+    }
+
+    fn at_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(w)) if w.eq_ignore_ascii_case(keyword))
+    }
+    fn keyword(&mut self, keyword: &str) -> bool {
+        let found = self.at_keyword(keyword);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), ParseError> {
+        if self.keyword(keyword) { Ok(()) } else { self.unexpected(keyword) }
+    }
+    fn symbol(&mut self, symbol: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Symbol(s)) if s == symbol);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+    fn expect_symbol(&mut self, symbol: &str) -> Result<(), ParseError> {
+        if self.symbol(symbol) { Ok(()) } else { self.unexpected(&format!("`{}`", symbol)) }
+    }
+    fn at_name(&self) -> bool {
+        matches!(self.peek(), Some(Token::Word(_)) | Some(Token::Quoted(_)))
+    }
+    fn name(&mut self) -> Result<String, ParseError> {
+        match self.peek() {
+            Some(Token::Word(n)) | Some(Token::Quoted(n)) => {
+                let n = n.clone();
+                self.position += 1;
+                Ok(n)
+            }
+            _ => self.unexpected("a name"),
+        }
+    }
+    fn names(&mut self) -> Result<Vec<String>, ParseError> {
+        let mut names = vec![self.name()?];
+        while self.symbol(",") {
+            names.push(self.name()?);
+        }
+        Ok(names)
+    }
+
+    fn value(&mut self) -> Result<AlbaTypes, ParseError> {
+        let value = match self.peek().cloned() {
+            Some(Token::Str(s)) => AlbaTypes::String(s),
+            Some(Token::Hex(b)) => AlbaTypes::Bytes(b),
+            Some(Token::Number(n)) => match number(&n) {
+                Some(v) => v,
+                None => return self.error(&format!("Invalid number `{}`", n)),
+            },
+            Some(Token::Word(w)) if w.eq_ignore_ascii_case("true") => AlbaTypes::Bool(true),
+            Some(Token::Word(w)) if w.eq_ignore_ascii_case("false") => AlbaTypes::Bool(false),
+            Some(Token::Word(w)) if w.eq_ignore_ascii_case("geo") => {
+                self.position += 1;
+                self.expect_symbol("(")?;
+                let lat = self.float()?;
+                self.expect_symbol(",")?;
+                let lon = self.float()?;
+                self.expect_symbol(")")?;
+                return Ok(AlbaTypes::Geo((lat, lon)));
+            }
+            _ => return self.unexpected("a value"),
+        };
+        self.position += 1;
+        Ok(value)
+    }
+    fn float(&mut self) -> Result<f64, ParseError> {
+        let v = match self.peek() {
+            Some(Token::Number(n)) => n.replace('_', "").trim_end_matches("f64").trim_end_matches("f32").parse::<f64>().ok(),
+            _ => return self.unexpected("a number"),
+        };
+        match v {
+            Some(v) => {
+                self.position += 1;
+                Ok(v)
+            }
+            None => self.error("Invalid coordinate"),
+        }
+    }
+    fn tuple(&mut self) -> Result<Vec<AlbaTypes>, ParseError> {
+        self.expect_symbol("(")?;
+        let mut values = vec![self.value()?];
+        while self.symbol(",") {
+            values.push(self.value()?);
+        }
+        self.expect_symbol(")")?;
+        Ok(values)
+    }
+
+    fn operator(&mut self) -> Result<LogicalOperator, ParseError> {
+        let spelling = match self.peek() {
+            Some(Token::Symbol(s)) if s == "!" => match self.tokens.get(self.position + 1).map(|t| &t.token) {
+                Some(Token::Word(w)) => {
+                    self.position += 1;
+                    format!("!{}", w.to_ascii_lowercase())
+                }
+                _ => s.clone(),
+            },
+            Some(Token::Symbol(s)) => s.clone(),
+            Some(Token::Word(w)) => w.to_ascii_lowercase(),
+            _ => return self.unexpected("an operator"),
+        };
+        match LogicalOperator::from_spelling(&spelling) {
+            Some(op) => {
+                self.position += 1;
+                Ok(op)
+            }
+            None => self.error(&format!("Unknown operator `{}`", spelling)),
+        }
+    }
+
+    /// `column operator operand (AND|OR column operator operand)*`
+    fn conditions(&mut self, search: bool) -> Result<Vec<ParsedCondition>, ParseError> {
+        let mut conditions = Vec::new();
+        let mut and = true;
+        loop {
+            let (line, column_number) = self.location();
+            let column = self.name()?;
+            let operator = self.operator()?;
+            let values = match operator {
+                LogicalOperator::In | LogicalOperator::NotIn => self.tuple()?,
+                LogicalOperator::Between => {
+                    let low = self.value()?;
+                    self.expect_keyword("and")?;
+                    vec![low, self.value()?]
+                }
+                _ => vec![self.value()?],
+            };
+            let at = |message: String| ParseError { line, column: column_number, message };
+            match lower(column.clone(), operator, values.clone()) {
+                Ok(Lowered::Filter(_)) if search => {
+                    return Err(at("The negated string operators are client-side filters of SearchBuilder, they have no Commands form".to_string()));
+                }
+                Ok(Lowered::Filter(_)) => return Err(at("The negated string operators are only supported by searches".to_string())),
+                Ok(_) => {}
+                Err(e) => return Err(at(e.to_string())),
+            }
+            conditions.push(ParsedCondition { column, operator, values, and, line, column_number });
+            if self.keyword("and") {
+                and = true;
+            } else if self.keyword("or") {
+                and = false;
+            } else {
+                return Ok(conditions);
+            }
+        }
+    }
+    fn where_clause(&mut self, search: bool) -> Result<Vec<ParsedCondition>, ParseError> {
+        if self.keyword("where") { self.conditions(search) } else { Ok(Vec::new()) }
+    }
+    /// The error of the condition at `at` the builder rejected, e.g. a lowered `IN` in a chain mixing AND and OR.
+    fn check_condition(at: (usize, usize), error: Option<&Error>) -> Result<(), ParseError> {
+        match error {
+            Some(e) => Err(ParseError { line: at.0, column: at.1, message: e.message().to_string() }),
+            None => Ok(()),
+        }
+    }
+    /// The position of the last condition when the lowered chain doesn't fit the 255 conditions of the protocol.
+    fn check_count(last: Option<(usize, usize)>, count: usize) -> Result<(), ParseError> {
+        match last {
+            Some((line, column)) if count > u8::MAX as usize => Err(ParseError {
+                line,
+                column,
+                message: "The condition chain is longer than 255 conditions".to_string(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    fn statement(&mut self) -> Result<Commands, ParseError> {
+        let keyword = match self.peek() {
+            Some(Token::Word(w)) => w.to_ascii_uppercase(),
+            _ => return self.unexpected("a statement"),
+        };
+        match keyword.as_str() {
+            "SELECT" => {
+                self.position += 1;
+                let columns = if self.symbol("*") { Vec::new() } else { self.names()? };
+                self.expect_keyword("from")?;
+                let mut builder = SearchBuilder::new().add_container(self.name()?);
+                for column in columns {
+                    builder = builder.add_column_name(column);
+                }
+                let conditions = self.where_clause(true)?;
+                let last = conditions.last().map(|c| (c.line, c.column_number));
+                for c in conditions {
+                    let at = (c.line, c.column_number);
+                    builder = builder.add_condition_values(c.column, c.operator, c.values, c.and);
+                    Self::check_condition(at, builder.error.as_ref())?;
+                }
+                Self::check_count(last, builder.conditions.0.len())?;
+                Ok(builder.into_batching_item())
+            }
+            "INSERT" => {
+                self.position += 1;
+                self.expect_keyword("into")?;
+                let container = self.name()?;
+                self.expect_symbol("(")?;
+                let col_nam = self.names()?;
+                self.expect_symbol(")")?;
+                self.expect_keyword("values")?;
+                let mut rows = Vec::new();
+                loop {
+                    let (line, column) = self.location();
+                    let row = self.tuple()?;
+                    if row.len() != col_nam.len() {
+                        return Err(ParseError {
+                            line,
+                            column,
+                            message: format!("Expected {} values, found {}", col_nam.len(), row.len()),
+                        });
+                    }
+                    rows.push(row);
+                    if !self.symbol(",") {
+                        break;
+                    }
+                }
+                if rows.len() == 1 {
+                    Ok(Commands::CreateRow(CreateRow { col_nam, col_val: rows.remove(0), container }))
+                } else {
+                    Ok(Commands::BatchCreateRows(BatchCreateRows { col_nam, col_val: rows, container }))
+                }
+            }
+            "UPDATE" => {
+                self.position += 1;
+                let mut builder = EditRowBuilder::new().put_container(self.name()?);
+                self.expect_keyword("set")?;
+                loop {
+                    let column = self.name()?;
+                    self.expect_symbol("=")?;
+                    builder = builder.edit_column(column, self.value()?);
+                    if !self.symbol(",") {
+                        break;
+                    }
+                }
+                let conditions = self.where_clause(false)?;
+                let last = conditions.last().map(|c| (c.line, c.column_number));
+                for c in conditions {
+                    let at = (c.line, c.column_number);
+                    builder = builder.add_condition_values(c.column, c.operator, c.values, c.and);
+                    Self::check_condition(at, builder.error.as_ref())?;
+                }
+                Self::check_count(last, builder.conditions.0.len())?;
+                Ok(builder.into_batching_item())
+            }
+            "DELETE" => {
+                self.position += 1;
+                self.expect_keyword("from")?;
+                let container = self.name()?;
+                let conditions = self.where_clause(false)?;
+                if conditions.is_empty() {
+                    return Ok(Commands::DeleteRow(DeleteRow { container, conditions: None }));
+                }
+                let last = conditions.last().map(|c| (c.line, c.column_number));
+                let mut builder = DeleteRowBuilder::new().put_container(container);
+                for c in conditions {
+                    let at = (c.line, c.column_number);
+                    builder = builder.add_condition_values(c.column, c.operator, c.values, c.and);
+                    Self::check_condition(at, builder.error.as_ref())?;
+                }
+                Self::check_count(last, builder.conditions.0.len())?;
+                Ok(builder.into_batching_item())
+            }
+            "CREATE" => {
+                self.position += 1;
+                self.expect_keyword("container")?;
+                let name = self.name()?;
+                let (col_nam, col_val) = self.columns()?.into_iter().unzip();
+                Ok(Commands::CreateContainer(CreateContainer { name, col_nam, col_val }))
+            }
+            "DROP" => {
+                self.position += 1;
+                self.expect_keyword("container")?;
+                Ok(Commands::DeleteContainer(DeleteContainer { container: self.name()? }))
+            }
+            "COMMIT" => {
+                self.position += 1;
+                Ok(Commands::Commit(Commit { container: self.optional_container()? }))
+            }
+            "ROLLBACK" => {
+                self.position += 1;
+                Ok(Commands::Rollback(Rollback { container: self.optional_container()? }))
+            }
+            "BEGIN" => {
+                self.position += 1;
+                let transaction = self.keyword("transaction");
+                self.symbol(";");
+                let mut commands = Vec::new();
+                while !self.keyword("end") {
+                    commands.push(self.statement()?);
+                    if !self.symbol(";") && !self.at_keyword("end") {
+                        return self.unexpected("`;` or END");
+                    }
+                }
+                Ok(Commands::Batch(Batch { transaction, commands }))
+            }
+            _ => self.unexpected("a statement"),
+        }
+    }
+    fn columns(&mut self) -> Result<Vec<(String, u8)>, ParseError> {
+        self.expect_symbol("(")?;
+        let mut columns = Vec::new();
+        loop {
+            columns.push((self.name()?, self.column_type()?));
+            if !self.symbol(",") {
+                break;
+            }
+        }
+        self.expect_symbol(")")?;
+        Ok(columns)
+    }
+    fn copy(&mut self) -> Result<CopyTransform, ParseError> {
+        self.expect_keyword("copy")?;
+        let from = self.name()?;
+        let from_columns = self.columns()?;
+        self.expect_keyword("into")?;
+        let to = self.name()?;
+        self.expect_symbol("(")?;
+        let (mut to_columns, mut sources) = (Vec::new(), Vec::new());
+        loop {
+            let column = self.name()?;
+            let column_type = self.column_type()?;
+            if self.keyword("from") {
+                sources.push((column.clone(), ColumnSource::Column(self.name()?)));
+            } else if self.keyword("default") {
+                sources.push((column.clone(), ColumnSource::Value(self.value()?)));
+            }
+            to_columns.push((column, column_type));
+            if !self.symbol(",") {
+                break;
+            }
+        }
+        self.expect_symbol(")")?;
+        fn borrowed(columns: &[(String, u8)]) -> Vec<(&str, u8)> {
+            columns.iter().map(|(c, t)| (c.as_str(), *t)).collect()
+        }
+        let copy = CopyTransform::new(&from, &borrowed(&from_columns), &to, &borrowed(&to_columns));
+        Ok(sources.into_iter().fold(copy, |copy, (column, source)| copy.column(&column, source)))
+    }
+    fn optional_container(&mut self) -> Result<Option<String>, ParseError> {
+        if self.at_name() && !self.at_keyword("end") { Ok(Some(self.name()?)) } else { Ok(None) }
+    }
+    fn column_type(&mut self) -> Result<u8, ParseError> {
+        let id = match self.peek() {
+            Some(Token::Word(w)) => column_type_id(w),
+            Some(Token::Number(n)) => n.parse::<u8>().ok(),
+            _ => return self.unexpected("a column type"),
+        };
+        match id {
+            Some(id) => {
+                self.position += 1;
+                Ok(id)
+            }
+            None => self.error(&format!("Unknown column type {}", self.describe())),
+        }
+    }
+}
+
+/// Parse a single statement of the text query language, an optional `;` may follow it.
+///
+/// ```text
+/// SELECT id, content FROM nice_container WHERE id >= 5 AND content &> 'legal'
+/// SELECT * FROM nice_container WHERE status in ('a', 'b') AND id between 1 and 9
+/// CREATE CONTAINER nice_container (id BIGINT, content MEDIUM_STRING, place GEO)
+/// INSERT INTO nice_container (id, content) VALUES (1, 'a'), (2, 'b')
+/// UPDATE nice_container SET content = 'c' WHERE id = 2
+/// DELETE FROM nice_container WHERE id != 1
+/// DROP CONTAINER nice_container
+/// COMMIT nice_container
+/// ROLLBACK
+/// BEGIN TRANSACTION; INSERT INTO nice_container (id) VALUES (3u64); COMMIT; END
+/// ```
+///
+/// Keywords and operator words are case insensitive, names are case sensitive and can be `"double quoted"`.
+/// The operators are the spellings accepted by the `lo!` macro, the ones TytoDB doesn't understand are lowered
+/// like the builders do. Conditions are not grouped, so an operator lowered into several conditions (a non string
+/// `in`, `not_in`, `between`) is only accepted in a chain joined only with OR (`in`) or only with AND. A row of several `INSERT` tuples becomes a `BatchCreateRows`, `BEGIN` starts a batch and
+/// `BEGIN TRANSACTION` a transaction batch, `SELECT *` sends an empty projection.
+///
+/// The values are `'strings'` (a doubled quote escapes itself), numbers, `true`/`false`, `x'00ff'` bytes and
+/// `geo(lat, lon)`. Integers are `I64` (or `I128`/`U128` when they don't fit) and decimals `F64`, unless they
+/// carry a Rust suffix: `5u8`, `5u16`, `5u32`, `5u64`, `5u128`, `5i32`, `5i64`, `5i128`, `1.5f32`, `1.5f64`.
+/// `--` starts a comment running to the end of the line.
+pub fn parse(input: &str) -> Result<Commands, ParseError> {
+    let mut parser = Parser::new(input)?;
+    let command = parser.statement()?;
+    parser.symbol(";");
+    if parser.peek().is_some() {
+        return parser.unexpected("the end of the input");
+    }
+    Ok(command)
+}
+
+/// Parse a script of `;` separated statements, see `parse` for the syntax.
+pub fn parse_script(input: &str) -> Result<Vec<Commands>, ParseError> {
+    let mut parser = Parser::new(input)?;
+    let mut commands = Vec::new();
+    while parser.peek().is_some() {
+        if parser.symbol(";") {
+            continue;
+        }
+        commands.push(parser.statement()?);
+        if parser.peek().is_some() && !parser.symbol(";") {
+            return parser.unexpected("`;`");
+        }
+    }
+    Ok(commands)
+}
+
+/// Parse the steps of a migration: `;` separated statements (see `parse`), `CREATE CONTAINER` and
+/// `DROP CONTAINER` being their own steps, plus `COPY` statements for `CopyTransform`s.
+///
+/// ```text
+/// COPY users (id BIGINT, name SMALL_STRING) INTO users_v2 (id BIGINT, name MEDIUM_STRING, nick SMALL_STRING FROM name, active BOOL DEFAULT true)
+/// ```
+///
+/// `COPY` lists the searched columns of the source and the columns of the created target, each taking the source
+/// column of the same name unless it names another with `FROM` or a constant with `DEFAULT`.
+pub fn parse_migration(input: &str) -> Result<Vec<Step>, ParseError> {
+    let mut parser = Parser::new(input)?;
+    let mut steps = Vec::new();
+    while parser.peek().is_some() {
+        if parser.symbol(";") {
+            continue;
+        }
+        if parser.at_keyword("copy") {
+            steps.push(Step::CopyTransform(parser.copy()?));
+        } else {
+            steps.push(Step::from(parser.statement()?));
+        }
+        if parser.peek().is_some() && !parser.symbol(";") {
+            return parser.unexpected("`;`");
+        }
+    }
+    Ok(steps)
+}
+
+impl FromStr for Commands {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Commands, ParseError> {
+        parse(s)
+    }
+}
+
+const KEYWORDS: [&str; 21] = [
+    "select", "from", "where", "insert", "into", "values", "update", "set", "delete", "create", "container", "drop", "commit",
+    "rollback", "begin", "transaction", "end", "and", "or", "copy", "default",
+];
+
+/// A name as written in the text query language, `"double quoted"` when it isn't a plain identifier or is a keyword.
+pub fn quote_name(name: &str) -> String {
+    let plain = name.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !KEYWORDS.iter().any(|k| k.eq_ignore_ascii_case(name));
+    if plain { name.to_string() } else { format!("\"{}\"", name.replace('"', "\"\"")) }
+}
+
+/// A value as written in the text query language, numbers always carry their type suffix so they parse back
+/// into the same `AlbaTypes` variant. Non finite floats have no literal form and are written as Rust does.
+pub fn literal(value: &AlbaTypes) -> String {
+    match value {
+        AlbaTypes::String(s) => format!("'{}'", s.replace('\'', "''")),
+        AlbaTypes::U8(v) => format!("{}u8", v),
+        AlbaTypes::U16(v) => format!("{}u16", v),
+        AlbaTypes::U32(v) => format!("{}u32", v),
+        AlbaTypes::U64(v) => format!("{}u64", v),
+        AlbaTypes::U128(v) => format!("{}u128", v),
+        AlbaTypes::I32(v) => format!("{}i32", v),
+        AlbaTypes::I64(v) => format!("{}i64", v),
+        AlbaTypes::I128(v) => format!("{}i128", v),
+        AlbaTypes::F32(v) => format!("{:?}f32", v),
+        AlbaTypes::F64(v) => format!("{:?}f64", v),
+        AlbaTypes::Bool(v) => v.to_string(),
+        AlbaTypes::Bytes(b) => format!("x'{}'", b.iter().map(|b| format!("{:02x}", b)).collect::<String>()),
+        AlbaTypes::Geo((lat, lon)) => format!("geo({:?}, {:?})", lat, lon),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::Search;
+
+    fn search(input: &str) -> Search {
+        match parse(input).unwrap() {
+            Commands::Search(search) => search,
+            other => panic!("not a search: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn round_trips_through_display() {
+        let statements = [
+            "SELECT id, content FROM nice_container WHERE id >= 5 AND content &> 'legal'",
+            "SELECT * FROM nice_container WHERE status in ('a', 'b') AND id between 1 and 9",
+            "SELECT id FROM c WHERE x in (1, 2, 3) OR y = 4u8",
+            "SELECT id FROM c WHERE x not_in (1, 2) AND name ^> 'pre' AND name $> 'fix.'",
+            "SELECT \"select\", \"a b\" FROM \"my \"\"c\"\"\" WHERE \"or\" != 'it''s'",
+            "CREATE CONTAINER nice_container (id BIGINT, content MEDIUM_STRING, place GEO, blob NANO_BYTES)",
+            "INSERT INTO c (a, b, c, d, e) VALUES (1, -2.5, true, x'00ff', geo(1.5, -2.25))",
+            "INSERT INTO c (a, b) VALUES (340282366920938463463374607431768211455, -170141183460469231731687303715884105728), (1u16, 2.5f32)",
+            "UPDATE c SET content = 'c', n = 1u32 WHERE id = 2 OR id = 3",
+            "DELETE FROM c WHERE id != 1",
+            "DELETE FROM c",
+            "DROP CONTAINER c",
+            "COMMIT c",
+            "ROLLBACK",
+            "BEGIN TRANSACTION; INSERT INTO c (id) VALUES (3u64); DELETE FROM c WHERE id = 1; END",
+            "BEGIN INSERT INTO c (id) VALUES (3u64) END",
+        ];
+        for statement in statements {
+            let command = parse(statement).unwrap_or_else(|e| panic!("{}: {}", statement, e));
+            let displayed = command.to_string();
+            let reparsed = parse(&displayed).unwrap_or_else(|e| panic!("{} -> {}: {}", statement, displayed, e));
+            assert_eq!(command, reparsed, "{} -> {}", statement, displayed);
+        }
+    }
+
+    #[test]
+    fn quoting() {
+        let parsed = search("SELECT \"from\", plain FROM \"my \"\"c\"\"\" WHERE s = 'it''s'");
+        assert_eq!(parsed.col_nam, vec!["from".to_string(), "plain".to_string()]);
+        assert_eq!(parsed.container, "my \"c\"");
+        assert_eq!(parsed.conditions.0[0].2, AlbaTypes::String("it's".into()));
+        assert_eq!(quote_name("plain_1"), "plain_1");
+        assert_eq!(quote_name("Select"), "\"Select\"");
+        assert_eq!(quote_name("a\"b"), "\"a\"\"b\"");
+        assert_eq!(quote_name("1a"), "\"1a\"");
+        assert_eq!(literal(&AlbaTypes::String("it's".into())), "'it''s'");
+        assert_eq!(literal(&AlbaTypes::Bytes(vec![0, 255])), "x'00ff'");
+    }
+
+    #[test]
+    fn errors_are_located() {
+        let error = |input: &str| parse(input).unwrap_err();
+        let e = error("SELECT id\nFROM c\nWHERE id ?? 1");
+        assert_eq!((e.line, e.column), (3, 10));
+        let e = error("SELECT id FROM c WHERE s = 'open");
+        assert_eq!((e.line, e.column, e.message.as_str()), (1, 28, "Unterminated string"));
+        let e = error("INSERT INTO c (a, b)\n  VALUES (1)");
+        assert_eq!((e.line, e.column), (2, 10));
+        assert_eq!(e.message, "Expected 2 values, found 1");
+        let e = error("SELECT id FROM c x");
+        assert_eq!((e.line, e.column), (1, 18));
+        let e = error("SELECT id FROM c WHERE b = x'0f0'");
+        assert_eq!((e.line, e.column), (1, 28));
+        let e = error("CREATE CONTAINER c (a NOT_A_TYPE)");
+        assert_eq!((e.line, e.column), (1, 23));
+        assert!(error("").message.contains("statement"));
+        assert_eq!(Error::from(error("DROP")).kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn in_and_between() {
+        let parsed = search("SELECT id FROM c WHERE x in (1, 2) OR y = 3");
+        assert_eq!(parsed.conditions.0.len(), 3);
+        assert!(parsed.conditions.1.iter().skip(1).all(|(_, g)| *g == 'o'));
+
+        let parsed = search("SELECT id FROM c WHERE s in ('a', 'b.c') AND id between 1 and 9");
+        assert_eq!(parsed.conditions.0[0].1, LogicalOperator::StringRegex);
+        assert_eq!(parsed.conditions.0[0].2, AlbaTypes::String("^(?:a|b\\.c)$".into()));
+        assert_eq!(parsed.conditions.0[1].1, LogicalOperator::HigherEquality);
+        assert_eq!(parsed.conditions.0[2].1, LogicalOperator::LowerEquality);
+        assert!(parsed.conditions.1.iter().all(|(_, g)| *g == 'a'));
+
+        let e = parse("SELECT id FROM c WHERE a = 1 AND x in (1, 2)").unwrap_err();
+        assert_eq!((e.line, e.column), (1, 34));
+        let e = parse("SELECT id FROM c WHERE s = 'a' OR id between 1 and 9").unwrap_err();
+        assert_eq!((e.line, e.column), (1, 35));
+        let e = parse("DELETE FROM c WHERE x in (1, 2)\nAND a = 1").unwrap_err();
+        assert_eq!((e.line, e.column), (2, 5));
+        let e = parse("SELECT id FROM c WHERE id between 1 9").unwrap_err();
+        assert_eq!((e.line, e.column), (1, 37));
+        let e = parse("SELECT id FROM c WHERE id <> 1").unwrap_err();
+        assert_eq!((e.line, e.column), (1, 27));
+    }
+}
@@ -0,0 +1,104 @@
+use std::time::Duration;
+
+use crate::{albastream::{Error, ErrorKind}, commands::Commands};
+
+/// How a client retries a request that failed, see `Client::with_retry_policy`.
+///
+/// A request is retried when its error kind is retryable, attempts are left and the per call `Retry` allows it.
+/// With `Retry::Auto` an empty reply is only retried for a search: the other commands may have been applied.
+/// The delay between attempts starts at the initial backoff and is multiplied after every attempt, up to the
+/// maximum backoff.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy{
+    max_attempts : u32,
+    initial_backoff : Duration,
+    max_backoff : Duration,
+    multiplier : f64,
+    retryable : Vec<ErrorKind>,
+}
+
+impl Default for RetryPolicy{
+    /// 3 attempts, backing off from 100ms up to 5s, retrying connection failures and empty replies to searches.
+    fn default() -> Self {
+        RetryPolicy{
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+            multiplier: 2.0,
+            retryable: vec![ErrorKind::Connection, ErrorKind::UnexpectedEof],
+        }
+    }
+}
+
+impl RetryPolicy{
+    pub fn new() -> Self{
+        Self::default()
+    }
+    /// A policy that never retries.
+    pub fn none() -> Self{
+        Self::default().max_attempts(1)
+    }
+    /// Total number of attempts, the first one included. `0` is treated as `1`.
+    pub fn max_attempts(mut self, attempts : u32) -> Self{
+        self.max_attempts = attempts.max(1);
+        self
+    }
+    pub fn backoff(mut self, initial : Duration, max : Duration) -> Self{
+        self.initial_backoff = initial;
+        self.max_backoff = max.max(initial);
+        self
+    }
+    pub fn multiplier(mut self, multiplier : f64) -> Self{
+        self.multiplier = multiplier.max(1.0);
+        self
+    }
+    /// Replace the error kinds worth retrying.
+    pub fn retry_on(mut self, kinds : &[ErrorKind]) -> Self{
+        self.retryable = kinds.to_vec();
+        self
+    }
+    pub fn attempts(&self) -> u32{
+        self.max_attempts
+    }
+    pub fn is_retryable(&self, error : &Error) -> bool{
+        self.retryable.contains(&error.kind())
+    }
+    /// The delay before the attempt following attempt number `attempt` (starting at 1).
+    pub fn delay(&self, attempt : u32) -> Duration{
+        let factor = self.multiplier.powi(attempt.saturating_sub(1).min(i32::MAX as u32) as i32);
+        self.initial_backoff.mul_f64(factor.min(u32::MAX as f64)).min(self.max_backoff)
+    }
+    /// Whether `compiled_command`, which failed with `error` on attempt number `attempt`, must be sent again.
+    #[cfg_attr(not(any(feature="thread", feature="tokio", feature="asyncstd")), allow(dead_code))]
+    pub(crate) fn should_retry(&self, retry : Retry, compiled_command : &[u8], attempt : u32, error : &Error) -> bool{
+        if attempt >= self.max_attempts || !self.is_retryable(error){
+            return false
+        }
+        match retry{
+            Retry::Auto => is_idempotent(compiled_command) && (error.kind() != ErrorKind::UnexpectedEof || compiled_command.first() == Some(&5)),
+            Retry::Always => true,
+            Retry::Never => false,
+        }
+    }
+}
+
+/// Per call override of the retries, see `Client::execute_with`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Retry{
+    /// Retry following the policy, only if the command is idempotent.
+    #[default]
+    Auto,
+    /// Retry following the policy, even if the command is not idempotent.
+    Always,
+    /// Send the command once.
+    Never,
+}
+
+/// `Commands::is_idempotent` for a compiled command, commands that can't be decoded are not.
+pub fn is_idempotent(compiled_command : &[u8]) -> bool{
+    match compiled_command.first(){
+        Some(2..=5) => true,
+        Some(9) => Commands::decompile(compiled_command).is_ok_and(|c| c.is_idempotent()),
+        _ => false,
+    }
+}
@@ -0,0 +1,129 @@
+use std::{fmt, io::BufRead, path::Path};
+
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::albastream::{Error, ErrorKind};
+
+/// The 32-byte secret key shared with the server, accepted by `Client::connect`.
+///
+/// The key is kept on the heap so that moving the secret doesn't leave copies behind, and overwritten with zeros
+/// when dropped. `Debug` doesn't print it. The buffers it is loaded through are zeroized too, not the `[u8;32]` it
+/// may be built from nor the copies `falcotcp` makes to open the connections.
+#[derive(Clone)]
+pub struct Secret{
+    key : Box<[u8;32]>,
+}
+
+impl Secret{
+    pub fn new(key : [u8;32]) -> Self{
+        Secret{key:Box::new(key)}
+    }
+    /// Read the key from a file holding exactly the 32 bytes of the key, or the key in hex or base64, see `decode`.
+    pub fn from_file<P : AsRef<Path>>(path : P) -> Result<Self,Error>{
+        let path = path.as_ref();
+        let bytes = Zeroizing::new(std::fs::read(path).map_err(|e| Error::new(ErrorKind::Other, &format!("Could not read the secret file {}: {}", path.display(), e)))?);
+        if bytes.len() == 32{
+            return Ok(Self::from_slice(&bytes))
+        }
+        match std::str::from_utf8(&bytes){
+            Ok(text) => Self::decode(text).map_err(|e| Error::new(ErrorKind::InvalidInput, &format!("The secret file {} holds neither 32 bytes nor a key in text: {}", path.display(), e.message()))),
+            Err(_) => Err(Error::new(ErrorKind::InvalidInput, &format!("The secret file {} holds {} bytes, a key is 32 bytes", path.display(), bytes.len()))),
+        }
+    }
+    /// Read the key from the environment variable `name`, in hex or base64, see `decode`.
+    pub fn from_env(name : &str) -> Result<Self,Error>{
+        let text = Zeroizing::new(std::env::var(name).map_err(|e| Error::new(ErrorKind::InvalidInput, &format!("Could not read the secret from ${}: {}", name, e)))?);
+        Self::decode(&text)
+    }
+    /// Read the key from the first line of the standard input, in hex or base64, see `decode`.
+    pub fn from_stdin() -> Result<Self,Error>{
+        let mut line = Zeroizing::new(String::new());
+        std::io::stdin().lock().read_line(&mut line).map_err(|e| Error::new(ErrorKind::Other, &format!("Could not read the secret from the standard input: {}", e)))?;
+        Self::decode(&line)
+    }
+    /// Decode a key written as 64 hex digits or in standard base64 (44 characters, the padding being optional),
+    /// surrounding whitespace ignored.
+    pub fn decode(text : &str) -> Result<Self,Error>{
+        let text = text.trim();
+        let key = match text.len(){
+            64 => decode_hex(text),
+            43 | 44 => decode_base64(text),
+            n => return Err(Error::new(ErrorKind::InvalidInput, &format!("A secret is 64 hex digits or 44 base64 characters, got {} characters", n))),
+        };
+        key.map(|key| Self::from_slice(&key)).ok_or_else(|| Error::new(ErrorKind::InvalidInput, "The secret is neither valid hex nor valid base64"))
+    }
+    /// Derive the key from a passphrase with Argon2id (19 MiB, 2 passes), the server must be given the same key.
+    /// `salt` is at least 8 bytes, e.g. the name of the deployment.
+    #[cfg(feature="kdf")]
+    pub fn derive(passphrase : &[u8], salt : &[u8]) -> Result<Self,Error>{
+        let mut key = Box::new([0u8;32]);
+        argon2::Argon2::default().hash_password_into(passphrase, salt, key.as_mut()).map_err(|e| Error::new(ErrorKind::InvalidInput, &format!("Could not derive the secret: {}", e)))?;
+        Ok(Secret{key})
+    }
+    pub fn expose(&self) -> &[u8;32]{
+        &self.key
+    }
+    fn from_slice(key : &[u8]) -> Self{
+        let mut secret = Secret{key:Box::new([0u8;32])};
+        secret.key.copy_from_slice(key);
+        secret
+    }
+}
+
+impl From<[u8;32]> for Secret{
+    fn from(key : [u8;32]) -> Self{
+        Secret::new(key)
+    }
+}
+
+impl Drop for Secret{
+    fn drop(&mut self){
+        self.key.zeroize();
+    }
+}
+
+impl fmt::Debug for Secret{
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result{
+        f.write_str("Secret(<redacted>)")
+    }
+}
+
+fn decode_hex(text : &str) -> Option<Zeroizing<Vec<u8>>>{
+    let mut key = Zeroizing::new(Vec::with_capacity(32));
+    for pair in text.as_bytes().chunks(2){
+        let high = (pair[0] as char).to_digit(16)?;
+        let low = (pair[1] as char).to_digit(16)?;
+        key.push((high << 4 | low) as u8);
+    }
+    Some(key)
+}
+
+fn decode_base64(text : &str) -> Option<Zeroizing<Vec<u8>>>{
+    let text = text.strip_suffix('=').unwrap_or(text);
+    if text.len() != 43{
+        return None
+    }
+    let mut key = Zeroizing::new(Vec::with_capacity(32));
+    let mut bits = Zeroizing::new(0u32);
+    for (i, c) in text.bytes().enumerate(){
+        let sextet = match c{
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        *bits = *bits << 6 | sextet as u32;
+        if i % 4 == 3{
+            key.extend_from_slice(&bits.to_be_bytes()[1..]);
+            *bits = 0;
+        }
+    }
+    // 43 characters: 10 groups of 4 and 3 characters holding 2 bytes and 2 zero bits
+    if *bits & 3 != 0{
+        return None
+    }
+    key.extend_from_slice(&(*bits >> 2).to_be_bytes()[2..]);
+    Some(key)
+}
@@ -1,6 +1,9 @@
+use std::{cmp::Ordering, fmt};
+
 use crate::{
     albastream::{Error, ErrorKind},
     dynamic_int::{DynamicInteger, vec_from_two_vec},
+    parser::literal,
 };
 
 #[derive(Debug, Clone, PartialEq)]
@@ -143,6 +146,24 @@ impl AlbaTypes {
     }
 }
 impl AlbaTypes {
+    /// Length of `as_bytes`, without encoding the value.
+    pub fn encoded_len(&self) -> usize {
+        let dynamic = |len: usize| match len {
+            n if n <= u8::MAX as usize => 2,
+            n if n <= u16::MAX as usize => 3,
+            n if n <= u32::MAX as usize => 5,
+            _ => 9,
+        };
+        1 + match self {
+            AlbaTypes::String(s) => dynamic(s.len()) + s.len(),
+            AlbaTypes::Bytes(b) => dynamic(b.len()) + b.len(),
+            AlbaTypes::U8(_) | AlbaTypes::Bool(_) => 1,
+            AlbaTypes::U16(_) => 2,
+            AlbaTypes::U32(_) | AlbaTypes::I32(_) | AlbaTypes::F32(_) => 4,
+            AlbaTypes::U64(_) | AlbaTypes::I64(_) | AlbaTypes::F64(_) => 8,
+            AlbaTypes::U128(_) | AlbaTypes::I128(_) | AlbaTypes::Geo(_) => 16,
+        }
+    }
     pub fn from_bytes(input: &[u8]) -> Result<(Self, usize), Error> {
         if input.is_empty() {
             return Err(Error::new(ErrorKind::InvalidInput, "Input is empty"));
@@ -298,6 +319,100 @@ impl AlbaTypes {
         }
     }
 }
+
+pub(crate) enum Numeric {
+    Signed(i128),
+    Unsigned(u128),
+    Float(f64),
+}
+impl Numeric {
+    fn cmp(&self, other: &Numeric) -> Ordering {
+        match (self, other) {
+            (Numeric::Signed(a), Numeric::Signed(b)) => a.cmp(b),
+            (Numeric::Unsigned(a), Numeric::Unsigned(b)) => a.cmp(b),
+            (Numeric::Signed(a), Numeric::Unsigned(b)) => {
+                if *a < 0 { Ordering::Less } else { (*a as u128).cmp(b) }
+            }
+            (Numeric::Unsigned(_), Numeric::Signed(_)) => other.cmp(self).reverse(),
+            (Numeric::Float(a), Numeric::Float(b)) => a.total_cmp(b),
+            (Numeric::Float(a), Numeric::Signed(b)) => float_cmp_integer(*a, *b < 0, b.unsigned_abs()),
+            (Numeric::Float(a), Numeric::Unsigned(b)) => float_cmp_integer(*a, false, *b),
+            (_, Numeric::Float(_)) => other.cmp(self).reverse(),
+        }
+    }
+}
+
+/// Compares a float with the integer `-magnitude` (when `negative`) or `magnitude` exactly, following
+/// `f64::total_cmp` for the values floats only have: NaNs are beyond the infinities and `-0.0` is below `0`.
+fn float_cmp_integer(float: f64, negative: bool, magnitude: u128) -> Ordering {
+    // 2^128, above every u128
+    const BEYOND: f64 = 340_282_366_920_938_463_463_374_607_431_768_211_456.0;
+    if float.is_nan() {
+        return if float.is_sign_negative() { Ordering::Less } else { Ordering::Greater };
+    }
+    if float == 0.0 && magnitude == 0 {
+        return if float.is_sign_negative() { Ordering::Less } else { Ordering::Equal };
+    }
+    let float_negative = float < 0.0;
+    if magnitude == 0 || float_negative != negative {
+        return if float_negative { Ordering::Less } else { Ordering::Greater };
+    }
+    let abs = float.abs();
+    let ordering = if abs >= BEYOND {
+        Ordering::Greater
+    } else {
+        // exact, the truncated value being an integer below 2^128
+        let whole = abs.trunc() as u128;
+        whole.cmp(&magnitude).then(if abs.fract() > 0.0 { Ordering::Greater } else { Ordering::Equal })
+    };
+    if negative { ordering.reverse() } else { ordering }
+}
+
+impl AlbaTypes {
+    pub(crate) fn numeric(&self) -> Option<Numeric> {
+        Some(match self {
+            AlbaTypes::U8(v) => Numeric::Unsigned(*v as u128),
+            AlbaTypes::U16(v) => Numeric::Unsigned(*v as u128),
+            AlbaTypes::U32(v) => Numeric::Unsigned(*v as u128),
+            AlbaTypes::U64(v) => Numeric::Unsigned(*v as u128),
+            AlbaTypes::U128(v) => Numeric::Unsigned(*v),
+            AlbaTypes::I32(v) => Numeric::Signed(*v as i128),
+            AlbaTypes::I64(v) => Numeric::Signed(*v as i128),
+            AlbaTypes::I128(v) => Numeric::Signed(*v),
+            AlbaTypes::F32(v) => Numeric::Float(*v as f64),
+            AlbaTypes::F64(v) => Numeric::Float(*v),
+            _ => return None,
+        })
+    }
+    fn rank(&self) -> u8 {
+        match self {
+            AlbaTypes::Bool(_) => 0,
+            AlbaTypes::String(_) => 2,
+            AlbaTypes::Bytes(_) => 3,
+            AlbaTypes::Geo(_) => 4,
+            _ => 1,
+        }
+    }
+
+    /// Total order over every `AlbaTypes` value.
+    ///
+    /// Numbers are compared exactly by value whatever their width or signedness (floats with `f64::total_cmp`),
+    /// strings and bytes lexicographically and geo values by their first coordinate, then the second.
+    /// Values of different kinds are ordered as `Bool < numbers < String < Bytes < Geo`.
+    pub fn total_cmp(&self, other: &AlbaTypes) -> Ordering {
+        if let (Some(a), Some(b)) = (self.numeric(), other.numeric()) {
+            return a.cmp(&b);
+        }
+        match (self, other) {
+            (AlbaTypes::Bool(a), AlbaTypes::Bool(b)) => a.cmp(b),
+            (AlbaTypes::String(a), AlbaTypes::String(b)) => a.cmp(b),
+            (AlbaTypes::Bytes(a), AlbaTypes::Bytes(b)) => a.cmp(b),
+            (AlbaTypes::Geo(a), AlbaTypes::Geo(b)) => a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+}
+
 pub trait ToAlbaAlbaTypes {
     fn to_alba_alba_types(&self) -> AlbaTypes;
 }
@@ -407,17 +522,17 @@ impl ToAlbaAlbaTypes for &[u8] {
 }
 impl ToAlbaAlbaTypes for u128 {
     fn to_alba_alba_types(&self) -> AlbaTypes {
-        AlbaTypes::U128(self.clone())
+        AlbaTypes::U128(*self)
     }
 }
 impl ToAlbaAlbaTypes for i128 {
     fn to_alba_alba_types(&self) -> AlbaTypes {
-        AlbaTypes::I128(self.clone())
+        AlbaTypes::I128(*self)
     }
 }
 impl ToAlbaAlbaTypes for (f64, f64) {
     fn to_alba_alba_types(&self) -> AlbaTypes {
-        AlbaTypes::Geo(self.clone())
+        AlbaTypes::Geo(*self)
     }
 }
 
@@ -788,3 +903,240 @@ pub const HUGE_INT: u8 = 33;
 ///
 /// **Usage in the database** (both disk and memory): 16 bytes
 pub const U_HUGE_INT: u8 = 34;
+
+/// Names of the column types, as spelled by the constants of this module.
+// `TEXT` is listed so that the containers still using it can be described
+#[allow(deprecated)]
+const COLUMN_TYPE_NAMES: [(&str, u8); 35] = [
+    ("NONE", NONE),
+    ("CHAR", CHAR),
+    ("INT", INT),
+    ("BIGINT", BIGINT),
+    ("BOOL", BOOL),
+    ("FLOAT", FLOAT),
+    ("TEXT", TEXT),
+    ("NANO_STRING", NANO_STRING),
+    ("SMALL_STRING", SMALL_STRING),
+    ("MEDIUM_STRING", MEDIUM_STRING),
+    ("BIG_STRING", BIG_STRING),
+    ("LARGE_STRING", LARGE_STRING),
+    ("NANO_BYTES", NANO_BYTES),
+    ("SMALL_BYTES", SMALL_BYTES),
+    ("MEDIUM_BYTES", MEDIUM_BYTES),
+    ("BIG_BYTES", BIG_BYTES),
+    ("LARGE_BYTES", LARGE_BYTES),
+    ("LIGHT_PASSWORD", LIGHT_PASSWORD),
+    ("MEDIUM_PASSWORD", MEDIUM_PASSWORD),
+    ("HEAVY_PASSWORD", HEAVY_PASSWORD),
+    ("EMAIL", EMAIL),
+    ("GEO", GEO),
+    ("SLICE4", SLICE4),
+    ("SLICE3", SLICE3),
+    ("SLICE2", SLICE2),
+    ("SLICE1", SLICE1),
+    ("SLICE0", SLICE0),
+    ("UINT", UINT),
+    ("UBIGINT", UBIGINT),
+    ("NANO_INT", NANO_INT),
+    ("U_NANO_INT", U_NANO_INT),
+    ("SHORT", SHORT),
+    ("U_SHORT", U_SHORT),
+    ("HUGE_INT", HUGE_INT),
+    ("U_HUGE_INT", U_HUGE_INT),
+];
+
+/// The column type id of a constant name of this module (e.g. `"MEDIUM_STRING"`), case insensitive.
+pub fn column_type_id(name: &str) -> Option<u8> {
+    COLUMN_TYPE_NAMES
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, id)| *id)
+}
+
+/// The constant name of a column type id, e.g. `9` is `"MEDIUM_STRING"`.
+pub fn column_type_name(id: u8) -> Option<&'static str> {
+    COLUMN_TYPE_NAMES.iter().find(|(_, i)| *i == id).map(|(n, _)| *n)
+}
+
+/// The `AlbaTypes` id of the values of a column type, e.g. `BIGINT` columns take `AlbaTypes::I64` (id 10).
+///
+/// `NANO_INT` and `SHORT` have no variant of their size and take `AlbaTypes::I32`, `NONE` takes nothing.
+#[allow(deprecated)]
+pub fn value_type_id(column_type: u8) -> Option<u8> {
+    Some(match column_type {
+        CHAR | TEXT | NANO_STRING | SMALL_STRING | MEDIUM_STRING | BIG_STRING | LARGE_STRING | EMAIL => 0,
+        NANO_BYTES | SMALL_BYTES | MEDIUM_BYTES | BIG_BYTES | LARGE_BYTES => 11,
+        LIGHT_PASSWORD | MEDIUM_PASSWORD | HEAVY_PASSWORD => 11,
+        SLICE0 | SLICE1 | SLICE2 | SLICE3 | SLICE4 => 11,
+        INT | NANO_INT | SHORT => 9,
+        BIGINT => 10,
+        HUGE_INT => 12,
+        U_NANO_INT => 1,
+        U_SHORT => 2,
+        UINT => 3,
+        UBIGINT => 4,
+        U_HUGE_INT => 5,
+        BOOL => 8,
+        FLOAT => 7,
+        GEO => 13,
+        _ => return None,
+    })
+}
+
+/// Convert `value` to the value type of `column_type` (see `value_type_id`), for a column changing type.
+///
+/// Numbers convert when they fit the target (floats only when integral), strings parse into numbers, booleans
+/// and back into text, booleans are `0`/`1`, and strings and bytes convert through UTF-8. Values of columns
+/// without a value type are kept as they are.
+pub fn convert_value(value: AlbaTypes, column_type: u8) -> Result<AlbaTypes, Error> {
+    let Some(target) = value_type_id(column_type) else { return Ok(value) };
+    if value.id() == target {
+        return Ok(value);
+    }
+    let converted = match (target, &value) {
+        (0, AlbaTypes::Bytes(b)) => String::from_utf8(b.clone()).ok().map(AlbaTypes::String),
+        (0, v) => Some(AlbaTypes::String(v.to_string())),
+        (11, AlbaTypes::String(s)) => Some(AlbaTypes::Bytes(s.as_bytes().to_vec())),
+        (8, AlbaTypes::String(s)) => match s.trim() {
+            "true" | "1" => Some(AlbaTypes::Bool(true)),
+            "false" | "0" => Some(AlbaTypes::Bool(false)),
+            _ => None,
+        },
+        (8, v) => match integer_value(v) {
+            Some(0) => Some(AlbaTypes::Bool(false)),
+            Some(1) => Some(AlbaTypes::Bool(true)),
+            _ => None,
+        },
+        (6, v) => float_value(v).map(|f| AlbaTypes::F32(f as f32)),
+        (7, v) => float_value(v).map(AlbaTypes::F64),
+        (_, v) => integer_value(v).and_then(|i| match target {
+            1 => u8::try_from(i).ok().map(AlbaTypes::U8),
+            2 => u16::try_from(i).ok().map(AlbaTypes::U16),
+            3 => u32::try_from(i).ok().map(AlbaTypes::U32),
+            4 => u64::try_from(i).ok().map(AlbaTypes::U64),
+            5 => u128::try_from(i).ok().map(AlbaTypes::U128),
+            9 => {
+                let fits = match column_type {
+                    NANO_INT => i8::try_from(i).is_ok(),
+                    SHORT => i16::try_from(i).is_ok(),
+                    _ => true,
+                };
+                i32::try_from(i).ok().filter(|_| fits).map(AlbaTypes::I32)
+            }
+            10 => i64::try_from(i).ok().map(AlbaTypes::I64),
+            12 => Some(AlbaTypes::I128(i)),
+            _ => None,
+        }),
+    };
+    converted.ok_or_else(|| {
+        Error::new(
+            ErrorKind::InvalidInput,
+            &format!("Can't convert {} to a {} column", literal(&value), column_type_name(column_type).unwrap_or("?")),
+        )
+    })
+}
+
+fn integer_value(value: &AlbaTypes) -> Option<i128> {
+    Some(match value {
+        AlbaTypes::U8(v) => *v as i128,
+        AlbaTypes::U16(v) => *v as i128,
+        AlbaTypes::U32(v) => *v as i128,
+        AlbaTypes::U64(v) => *v as i128,
+        AlbaTypes::U128(v) => i128::try_from(*v).ok()?,
+        AlbaTypes::I32(v) => *v as i128,
+        AlbaTypes::I64(v) => *v as i128,
+        AlbaTypes::I128(v) => *v,
+        AlbaTypes::Bool(v) => *v as i128,
+        AlbaTypes::F32(v) => return float_integer(*v as f64),
+        AlbaTypes::F64(v) => return float_integer(*v),
+        AlbaTypes::String(s) => s.trim().parse().ok()?,
+        AlbaTypes::Bytes(_) | AlbaTypes::Geo(_) => return None,
+    })
+}
+
+fn float_integer(f: f64) -> Option<i128> {
+    (f.is_finite() && f.fract() == 0.0 && f.abs() < 2f64.powi(127)).then_some(f as i128)
+}
+
+fn float_value(value: &AlbaTypes) -> Option<f64> {
+    Some(match value {
+        AlbaTypes::F32(v) => *v as f64,
+        AlbaTypes::F64(v) => *v,
+        AlbaTypes::String(s) => s.trim().parse().ok()?,
+        AlbaTypes::Bytes(_) | AlbaTypes::Geo(_) | AlbaTypes::Bool(_) => return None,
+        v => integer_value(v)? as f64,
+    })
+}
+
+/// The plain value: strings unquoted, numbers without type suffix, bytes as `x'00ff'` and geo as `(lat, lon)`.
+/// See `parser::literal` for the typed form.
+impl fmt::Display for AlbaTypes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AlbaTypes::String(s) => write!(f, "{}", s),
+            AlbaTypes::U8(v) => write!(f, "{}", v),
+            AlbaTypes::U16(v) => write!(f, "{}", v),
+            AlbaTypes::U32(v) => write!(f, "{}", v),
+            AlbaTypes::U64(v) => write!(f, "{}", v),
+            AlbaTypes::U128(v) => write!(f, "{}", v),
+            AlbaTypes::F32(v) => write!(f, "{}", v),
+            AlbaTypes::F64(v) => write!(f, "{}", v),
+            AlbaTypes::Bool(v) => write!(f, "{}", v),
+            AlbaTypes::I32(v) => write!(f, "{}", v),
+            AlbaTypes::I64(v) => write!(f, "{}", v),
+            AlbaTypes::Bytes(b) => {
+                write!(f, "x'")?;
+                for b in b {
+                    write!(f, "{:02x}", b)?;
+                }
+                write!(f, "'")
+            }
+            AlbaTypes::I128(v) => write!(f, "{}", v),
+            AlbaTypes::Geo((lat, lon)) => write!(f, "({}, {})", lat, lon),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn total_cmp_compares_floats_and_integers_exactly() {
+        let two_53 = 9_007_199_254_740_992u64;
+        let values = [AlbaTypes::U64(two_53 + 1), AlbaTypes::F64(two_53 as f64), AlbaTypes::U64(two_53)];
+        assert_eq!(values[0].total_cmp(&values[1]), Ordering::Greater);
+        assert_eq!(values[1].total_cmp(&values[2]), Ordering::Equal);
+        assert_eq!(values[0].total_cmp(&values[2]), Ordering::Greater);
+
+        assert_eq!(AlbaTypes::F64(-0.5).total_cmp(&AlbaTypes::I32(0)), Ordering::Less);
+        assert_eq!(AlbaTypes::F64(-1.5).total_cmp(&AlbaTypes::I32(-1)), Ordering::Less);
+        assert_eq!(AlbaTypes::F64(-0.0).total_cmp(&AlbaTypes::U8(0)), Ordering::Less);
+        assert_eq!(AlbaTypes::F64(0.0).total_cmp(&AlbaTypes::U8(0)), Ordering::Equal);
+        assert_eq!(AlbaTypes::F64(f64::INFINITY).total_cmp(&AlbaTypes::U128(u128::MAX)), Ordering::Greater);
+        assert_eq!(AlbaTypes::F64(f64::NAN).total_cmp(&AlbaTypes::U128(u128::MAX)), Ordering::Greater);
+        assert_eq!(AlbaTypes::I128(i128::MIN).total_cmp(&AlbaTypes::F64(-f64::NAN)), Ordering::Greater);
+        assert_eq!(AlbaTypes::I128(i128::MIN).total_cmp(&AlbaTypes::F64(-1e39)), Ordering::Greater);
+    }
+
+    #[test]
+    fn total_cmp_sorts_mixed_numbers() {
+        let mut values: Vec<AlbaTypes> = Vec::new();
+        for i in 0..40u64 {
+            let n = (1u64 << 53) - 20 + i;
+            values.push(AlbaTypes::U64(n));
+            values.push(AlbaTypes::I64(-(n as i64)));
+            values.push(AlbaTypes::F64(n as f64));
+            values.push(AlbaTypes::F64(-(n as f64) - 0.5));
+        }
+        values.sort_by(|a, b| a.total_cmp(b));
+        for pair in values.windows(2) {
+            assert_ne!(pair[0].total_cmp(&pair[1]), Ordering::Greater);
+        }
+        for a in &values {
+            for b in &values {
+                assert_eq!(a.total_cmp(b), b.total_cmp(a).reverse());
+            }
+        }
+    }
+}